@@ -0,0 +1,103 @@
+//! Assertions that report as failed.
+//!
+//! The panic hook can only tell an assertion from any other panic by its
+//! message, and `assert!(cond, "...")` with a custom message panics exactly like
+//! `panic!("...")`. The macros of this module panic with the messages of their
+//! `std` counterparts, but mark the panic as an assertion failure, so the test or
+//! step is reported as failed whatever the message.
+//!
+//! `#[allure_test]`, `#[step]` and `#[allure_fixture]` use them in place of the
+//! `std` ones in the bodies they wrap. Elsewhere, e.g. in helper functions, bring
+//! them into scope to shadow the prelude: `use allure_rust::{assert, assert_eq};`.
+//! The operands are evaluated before the panic is marked, so a panic while
+//! evaluating them is still broken.
+
+use crate::panic_hook;
+
+/// Runs `panic` with its panic marked as an assertion failure. Used by the macros.
+#[doc(hidden)]
+pub fn fail(panic: impl FnOnce()) {
+    panic_hook::asserting(panic);
+}
+
+/// Like [`std::assert!`], reported as failed even with a custom message.
+#[macro_export]
+macro_rules! assert {
+    ($cond:expr $(,)?) => {
+        if !$cond {
+            allure_rust::assertion::fail(|| {
+                ::core::panic!(
+                    "assertion failed: {}",
+                    ::core::stringify!($cond)
+                )
+            });
+        }
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            allure_rust::assertion::fail(|| ::core::panic!($($arg)+));
+        }
+    };
+}
+
+/// Like [`std::assert_eq!`], reported as failed.
+#[macro_export]
+macro_rules! assert_eq {
+    ($left:expr, $right:expr $(, $($arg:tt)*)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    allure_rust::assertion::fail(|| {
+                        ::core::assert_eq!(left, right $(, $($arg)*)?)
+                    });
+                }
+            }
+        }
+    };
+}
+
+/// Like [`std::assert_ne!`], reported as failed.
+#[macro_export]
+macro_rules! assert_ne {
+    ($left:expr, $right:expr $(, $($arg:tt)*)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if *left == *right {
+                    allure_rust::assertion::fail(|| {
+                        ::core::assert_ne!(left, right $(, $($arg)*)?)
+                    });
+                }
+            }
+        }
+    };
+}
+
+/// Like [`std::debug_assert!`], reported as failed.
+#[macro_export]
+macro_rules! debug_assert {
+    ($($arg:tt)*) => {
+        if ::core::cfg!(debug_assertions) {
+            allure_rust::assert!($($arg)*);
+        }
+    };
+}
+
+/// Like [`std::debug_assert_eq!`], reported as failed.
+#[macro_export]
+macro_rules! debug_assert_eq {
+    ($($arg:tt)*) => {
+        if ::core::cfg!(debug_assertions) {
+            allure_rust::assert_eq!($($arg)*);
+        }
+    };
+}
+
+/// Like [`std::debug_assert_ne!`], reported as failed.
+#[macro_export]
+macro_rules! debug_assert_ne {
+    ($($arg:tt)*) => {
+        if ::core::cfg!(debug_assertions) {
+            allure_rust::assert_ne!($($arg)*);
+        }
+    };
+}
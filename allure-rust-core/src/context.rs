@@ -0,0 +1,437 @@
+//! The state of the running test.
+//!
+//! A test's context is shared behind a [`TestHandle`], and each thread has a
+//! current handle that the runtime functions work with. The handle also knows
+//! the innermost step of its flow of execution, so concurrent flows of one test
+//! nest their steps independently. Futures carry their handle with them (see
+//! [`crate::future`]), so a test keeps its context whichever thread polls it.
+
+use crate::models;
+use crate::panic_hook::{self, PanicKind, PanicRecord};
+use chrono::Utc;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+use uuid::Uuid;
+
+pub(crate) struct TestContext {
+    pub uuid: Uuid,
+    // Every step of the test, indexed by id, and the ids of the top-level ones
+    pub steps: Vec<StepNode>,
+    pub root_steps: Vec<usize>,
+    pub attachments: Vec<models::Attachment>,
+    pub suites: crate::suite::Suites,
+    // Where the test is defined, for `fullName` and its package labels
+    pub module_path: Option<&'static str>,
+    pub function: Option<&'static str>,
+    pub description: Option<String>,
+    pub description_html: Option<String>,
+    pub labels: Vec<models::Label>,
+    pub links: Vec<models::Link>,
+    pub parameters: Vec<models::Parameter>,
+    // Fixtures that finished, for the container of the test
+    pub befores: Vec<models::FixtureResult>,
+    pub afters: Vec<models::FixtureResult>,
+    // From the start of the test to its end; a throwaway context never runs
+    pub running: bool,
+    pub panic: Option<PanicRecord>,
+    // Counts the steps and fan-outs of the test, to order steps by
+    next_order: u64,
+    // Wall-clock start for the report, monotonic clock for the duration
+    pub start: i64,
+    started_at: Instant,
+}
+
+impl TestContext {
+    pub fn new() -> Self {
+        TestContext {
+            uuid: Uuid::new_v4(),
+            steps: Vec::new(),
+            root_steps: Vec::new(),
+            attachments: Vec::new(),
+            suites: Default::default(),
+            module_path: None,
+            function: None,
+            description: None,
+            description_html: None,
+            labels: Vec::new(),
+            links: Vec::new(),
+            parameters: Vec::new(),
+            befores: Vec::new(),
+            afters: Vec::new(),
+            running: false,
+            panic: None,
+            next_order: 0,
+            start: Utc::now().timestamp_millis(),
+            started_at: Instant::now(),
+        }
+    }
+
+    pub fn elapsed_millis(&self) -> i64 {
+        i64::try_from(self.started_at.elapsed().as_millis()).unwrap_or(i64::MAX)
+    }
+
+    /// Adds a running step under the innermost step of `cursor` and returns its
+    /// id. Children are kept in the order they were started, except that the
+    /// steps of a fan-out are ordered by the index of their branch.
+    pub fn add_step(&mut self, cursor: &Cursor, step: models::TestStep) -> usize {
+        let order = self.next_order();
+        let key = match cursor.branch {
+            Some((fork, index)) => (fork, index, order),
+            None => (order, 0, order),
+        };
+
+        let siblings = match cursor.step {
+            Some(parent) => &self.steps[parent].children,
+            None => &self.root_steps,
+        };
+        let position = siblings.partition_point(|&sibling| self.steps[sibling].key <= key);
+
+        let id = self.steps.len();
+        self.steps.push(StepNode {
+            step,
+            parent: cursor.step,
+            key,
+            children: Vec::new(),
+        });
+        match cursor.step {
+            Some(parent) => self.steps[parent].children.insert(position, id),
+            None => self.root_steps.insert(position, id),
+        }
+        id
+    }
+
+    /// Records misuse of the runtime, e.g. an unbalanced `end_step()`, as a
+    /// broken step under the innermost step of `cursor`: it shows up in the
+    /// report where it happened, without failing the test.
+    pub fn add_misuse(&mut self, cursor: &Cursor, name: &str, message: &str) {
+        let now = Utc::now().timestamp_millis();
+        let step = models::TestStep {
+            name: name.to_string(),
+            description: None,
+            description_html: None,
+            status: models::Status::Broken,
+            status_details: Some(models::StatusDetails {
+                message: Some(message.to_string()),
+                trace: None,
+            }),
+            stage: "finished".to_string(),
+            start: now,
+            stop: now,
+            steps: Vec::new(),
+            attachments: Vec::new(),
+            parameters: Vec::new(),
+        };
+        self.add_step(cursor, step);
+    }
+
+    /// Adds a running fixture and returns its id. Its steps nest in it like in
+    /// a step, but it is not one of the steps of the test.
+    pub fn add_fixture(&mut self, cursor: &Cursor, fixture: models::TestStep) -> usize {
+        let order = self.next_order();
+        let id = self.steps.len();
+        self.steps.push(StepNode {
+            step: fixture,
+            parent: cursor.step,
+            key: (order, 0, order),
+            children: Vec::new(),
+        });
+        id
+    }
+
+    pub fn next_order(&mut self) -> u64 {
+        self.next_order += 1;
+        self.next_order
+    }
+
+    /// The top-level steps with their nested steps filled in.
+    pub fn step_tree(&self) -> Vec<models::TestStep> {
+        self.subtree(&self.root_steps)
+    }
+
+    /// Step `id` with its nested steps filled in.
+    pub fn step_with_children(&self, id: usize) -> models::TestStep {
+        self.subtree(&[id]).remove(0)
+    }
+
+    fn subtree(&self, ids: &[usize]) -> Vec<models::TestStep> {
+        ids.iter()
+            .map(|&id| {
+                let node = &self.steps[id];
+                models::TestStep {
+                    steps: self.subtree(&node.children),
+                    ..node.step.clone()
+                }
+            })
+            .collect()
+    }
+
+    /// Status of a test or step that unwound with `payload`: assertion failures are
+    /// `Failed`, any other panic is `Broken`.
+    pub fn failure(
+        &self,
+        payload: &(dyn Any + Send),
+        default_message: &str,
+    ) -> (models::Status, models::StatusDetails) {
+        let message = panic_hook::payload_message(payload);
+        // Only trust the hook's record if it belongs to this panic
+        let record = self
+            .panic
+            .as_ref()
+            .filter(|record| Some(&record.message) == message.as_ref());
+        let kind = match (record, &message) {
+            (Some(record), _) => record.kind,
+            (None, Some(message)) => panic_hook::classify(message),
+            (None, None) => PanicKind::Other,
+        };
+        let status = match kind {
+            PanicKind::Assertion => models::Status::Failed,
+            PanicKind::Other => models::Status::Broken,
+        };
+        (
+            status,
+            models::StatusDetails {
+                message: Some(message.unwrap_or_else(|| default_message.to_string())),
+                trace: record.map(PanicRecord::trace),
+            },
+        )
+    }
+
+    /// Status of a fixture dropped while unwinding, whose payload is out of
+    /// reach: the panic the hook recorded last is taken to be the one.
+    pub fn unwinding(&self, default_message: &str) -> (models::Status, models::StatusDetails) {
+        let status = match self.panic.as_ref().map(|record| record.kind) {
+            Some(PanicKind::Assertion) => models::Status::Failed,
+            _ => models::Status::Broken,
+        };
+        (
+            status,
+            models::StatusDetails {
+                message: Some(
+                    self.panic
+                        .as_ref()
+                        .map(|record| record.message.clone())
+                        .unwrap_or_else(|| default_message.to_string()),
+                ),
+                trace: self.panic.as_ref().map(PanicRecord::trace),
+            },
+        )
+    }
+}
+
+pub(crate) struct StepNode {
+    pub step: models::TestStep,
+    pub parent: Option<usize>,
+    key: (u64, usize, u64),
+    children: Vec<usize>,
+}
+
+/// Where one flow of execution is in the step tree.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Cursor {
+    /// The innermost running step.
+    pub step: Option<usize>,
+    /// The step the flow was handed over in: a flow only finishes the steps it
+    /// started itself.
+    pub base: Option<usize>,
+    /// For a branch of a fan-out, e.g. a rayon job: the order of the fan-out
+    /// and the index of the branch in it.
+    pub branch: Option<(u64, usize)>,
+}
+
+/// A handle to a running test, at the step that was running when the handle
+/// was taken.
+///
+/// The runtime functions record into the test that is current on the calling
+/// thread. Take a handle with [`current_test`](crate::current_test) and
+/// [`run`](TestHandle::run) code with it on another thread to record into the
+/// same test, e.g. with the helpers of [`crate::thread`]. Steps started there are
+/// nested in the step the handle was taken in.
+#[derive(Clone)]
+pub struct TestHandle {
+    context: Arc<Mutex<TestContext>>,
+    cursor: Cursor,
+}
+
+impl TestHandle {
+    fn new() -> Self {
+        TestHandle {
+            context: Arc::new(Mutex::new(TestContext::new())),
+            cursor: Cursor::default(),
+        }
+    }
+
+    /// Runs `f` with this test as the current test of the calling thread.
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _restore = Restore::enter(Some(self.clone()));
+        f()
+    }
+
+    /// Reserves the position of a fan-out among the steps started so far; see
+    /// [`TestHandle::branch`].
+    #[cfg(feature = "rayon")]
+    pub(crate) fn fork(&self) -> u64 {
+        self.lock().next_order()
+    }
+
+    /// A handle for branch `index` of fan-out `fork`, whose steps are ordered by
+    /// `index` rather than by when they started.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn branch(&self, fork: u64, index: usize) -> TestHandle {
+        TestHandle {
+            context: Arc::clone(&self.context),
+            cursor: Cursor {
+                branch: Some((fork, index)),
+                ..self.flow().cursor
+            },
+        }
+    }
+
+    /// A handle for a new flow of execution starting at the current step.
+    fn flow(&self) -> TestHandle {
+        TestHandle {
+            context: Arc::clone(&self.context),
+            cursor: Cursor {
+                base: self.cursor.step,
+                ..self.cursor
+            },
+        }
+    }
+
+    // A panic while the context was locked must not hide the rest of the test
+    fn lock(&self) -> MutexGuard<'_, TestContext> {
+        self.context.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<TestHandle>> = const { RefCell::new(None) };
+    // Set while this thread holds the lock of its context
+    static LOCKED: Cell<bool> = const { Cell::new(false) };
+    // Setups that finished outside of a test, for the next one to take over
+    static PENDING_BEFORES: RefCell<Vec<models::FixtureResult>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Makes a fresh context current on this thread. Setups that finished outside
+/// of a test before, e.g. those of rstest fixtures, are taken over by it.
+pub(crate) fn start() {
+    replace_current(Some(TestHandle::new()));
+    let befores = PENDING_BEFORES.with(RefCell::take);
+    with_context(|context| {
+        context.running = true;
+        context.befores = befores;
+    });
+}
+
+/// Keeps a setup that finished outside of a test for the next test to start on
+/// this thread.
+pub(crate) fn add_pending_before(fixture: models::FixtureResult) {
+    PENDING_BEFORES.with(|pending| pending.borrow_mut().push(fixture));
+}
+
+/// Runs `f`, e.g. a whole test, as if nothing was running on this thread: the
+/// current handle and the pending setups are put back afterwards.
+pub(crate) fn isolated<R>(f: impl FnOnce() -> R) -> R {
+    let _restore = Restore::enter(None);
+    let pending = PENDING_BEFORES.with(RefCell::take);
+    let result = f();
+    PENDING_BEFORES.with(|befores| *befores.borrow_mut() = pending);
+    result
+}
+
+/// Runs `f` with the current test's context. Outside of a test a throwaway
+/// context is created, so the runtime functions never fail.
+pub(crate) fn with_context<R>(f: impl FnOnce(&mut TestContext) -> R) -> R {
+    with_step(|context, _| f(context))
+}
+
+/// Like [`with_context`], also passing the cursor of this thread's flow, whose
+/// innermost step `f` may move.
+pub(crate) fn with_step<R>(f: impl FnOnce(&mut TestContext, &mut Cursor) -> R) -> R {
+    CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        let handle = current.get_or_insert_with(TestHandle::new);
+        let mut context = handle
+            .context
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        LOCKED.with(|locked| locked.set(true));
+        let _unlock = Unlock;
+        f(&mut context, &mut handle.cursor)
+    })
+}
+
+struct Unlock;
+
+impl Drop for Unlock {
+    fn drop(&mut self) {
+        LOCKED.with(|locked| locked.set(false));
+    }
+}
+
+/// The current handle of this thread, if a test was started on it.
+pub(crate) fn current() -> Option<TestHandle> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// A handle for a new flow of the current test of this thread, creating a
+/// throwaway test outside of one.
+pub(crate) fn current_flow() -> TestHandle {
+    CURRENT.with(|current| {
+        current
+            .borrow_mut()
+            .get_or_insert_with(TestHandle::new)
+            .flow()
+    })
+}
+
+/// Replaces the current handle of this thread, returning the previous one.
+pub(crate) fn replace_current(handle: Option<TestHandle>) -> Option<TestHandle> {
+    CURRENT.with(|current| current.replace(handle))
+}
+
+/// Puts back the handle a thread had before another one was made current, even
+/// if the code in between panics.
+pub(crate) struct Restore(Option<Option<TestHandle>>);
+
+impl Restore {
+    /// Makes `handle` current until the guard is dropped.
+    pub fn enter(handle: Option<TestHandle>) -> Self {
+        Restore(Some(replace_current(handle)))
+    }
+
+    /// Guards a current handle that already replaced `previous`.
+    pub fn previous(previous: Option<TestHandle>) -> Self {
+        Restore(Some(previous))
+    }
+
+    /// Puts back the previous handle, returning the one that was current.
+    pub fn finish(mut self) -> Option<TestHandle> {
+        replace_current(self.0.take().flatten())
+    }
+}
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            replace_current(previous);
+        }
+    }
+}
+
+// Called from the panic hook, so it must not panic itself. The record is only
+// built, backtrace and all, when a test is current
+pub(crate) fn record_panic(record: impl FnOnce() -> PanicRecord) {
+    // A panic inside the runtime itself would deadlock on the held lock
+    if LOCKED.try_with(Cell::get).unwrap_or(true) {
+        return;
+    }
+    let handle = CURRENT
+        .try_with(|current| current.try_borrow().ok().and_then(|handle| handle.clone()))
+        .ok()
+        .flatten();
+    if let Some(handle) = handle {
+        handle.lock().panic = Some(record());
+    }
+}
@@ -0,0 +1,92 @@
+//! Status details for errors returned from `#[allure_test]` functions and
+//! `allure_step!` blocks.
+//!
+//! The macros call `(&&ErrorReport(&error)).status_details()`, and method
+//! resolution picks the most specific implementation the error type supports:
+//! an `Error` itself, a pointer to an `Error` (`Box<dyn Error>`, `anyhow::Error`),
+//! or anything `Debug`, which is all libtest requires.
+//!
+//! A step block may evaluate to anything, so `allure_step!` first asks
+//! `(&&StepOutput(&value)).step_error()` for the error of a `Result`.
+
+use crate::models::StatusDetails;
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt::Debug;
+use std::ops::Deref;
+
+pub struct ErrorReport<'a, E>(pub &'a E);
+
+pub trait ErrorDetails {
+    fn status_details(&self) -> StatusDetails;
+}
+
+impl<E: Error> ErrorDetails for &&ErrorReport<'_, E> {
+    fn status_details(&self) -> StatusDetails {
+        describe(self.0)
+    }
+}
+
+impl<E> ErrorDetails for &ErrorReport<'_, E>
+where
+    E: Deref,
+    E::Target: Error,
+{
+    fn status_details(&self) -> StatusDetails {
+        describe(self.0.deref())
+    }
+}
+
+impl<E: Debug> ErrorDetails for ErrorReport<'_, E> {
+    fn status_details(&self) -> StatusDetails {
+        StatusDetails {
+            message: Some(format!("{:?}", self.0)),
+            trace: None,
+        }
+    }
+}
+
+pub struct StepOutput<'a, T>(pub &'a T);
+
+pub trait StepError<'a> {
+    type Error;
+
+    fn step_error(&self) -> Option<&'a Self::Error>;
+}
+
+impl<'a, T, E> StepError<'a> for &StepOutput<'a, Result<T, E>> {
+    type Error = E;
+
+    fn step_error(&self) -> Option<&'a E> {
+        self.0.as_ref().err()
+    }
+}
+
+impl<'a, T> StepError<'a> for StepOutput<'a, T> {
+    type Error = Infallible;
+
+    fn step_error(&self) -> Option<&'a Infallible> {
+        None
+    }
+}
+
+// The message is the `Display` of the error, the trace its `Debug` followed by
+// the `source()` chain
+fn describe<E: Error + ?Sized>(error: &E) -> StatusDetails {
+    let mut trace = format!("{:?}", error);
+    let mut source = error.source();
+    if source.is_some() {
+        trace.push_str("\n\nCaused by:");
+    }
+    let mut index = 0;
+    while let Some(cause) = source {
+        trace.push_str(&format!("\n    {}: {}", index, cause));
+        source = cause.source();
+        index += 1;
+    }
+
+    StatusDetails {
+        message: Some(error.to_string()),
+        trace: Some(trace),
+    }
+}
@@ -0,0 +1,148 @@
+//! Setup and teardown fixtures.
+//!
+//! A fixture is recorded like a step, with its own steps, attachments,
+//! parameters and status, but it goes to the `befores` or `afters` of a
+//! container rather than into the test result. When a test that ran fixtures
+//! ends, its container is written next to its result and linked to it by the
+//! test's uuid.
+//!
+//! Setups that run outside of a test, like rstest fixtures that are resolved
+//! before the test function runs, are taken over by the next test that starts
+//! on the same thread. Teardowns have to finish before the test ends.
+
+use crate::context::{self, with_context, with_step};
+use crate::models;
+use chrono::Utc;
+use uuid::Uuid;
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Setup,
+    Teardown,
+}
+
+/// A running fixture, which finishes when the guard is dropped: as failed or
+/// broken if it is dropped by a panic, as passed otherwise.
+#[must_use = "the fixture finishes as soon as the guard is dropped"]
+pub struct Fixture {
+    kind: Kind,
+    // The node of the fixture in the step arena of `test`
+    id: usize,
+    test: Uuid,
+    // A setup that started outside of a test, for the next test to take over
+    pending: bool,
+    finished: bool,
+}
+
+/// Starts a setup fixture, recorded in the `befores` of the test's container.
+pub fn setup(name: impl Into<String>) -> Fixture {
+    start(Kind::Setup, name.into())
+}
+
+/// Starts a teardown fixture, recorded in the `afters` of the test's container.
+pub fn teardown(name: impl Into<String>) -> Fixture {
+    start(Kind::Teardown, name.into())
+}
+
+fn start(kind: Kind, name: String) -> Fixture {
+    // A setup may run before the test that installs the hook starts
+    crate::panic_hook::install();
+
+    with_step(|context, cursor| {
+        let fixture = models::TestStep {
+            name,
+            description: None,
+            description_html: None,
+            status: models::Status::Passed,
+            status_details: None,
+            stage: "running".to_string(),
+            start: Utc::now().timestamp_millis(),
+            stop: 0,
+            steps: Vec::new(),
+            attachments: Vec::new(),
+            parameters: Vec::new(),
+        };
+        let id = context.add_fixture(cursor, fixture);
+        cursor.step = Some(id);
+        Fixture {
+            kind,
+            id,
+            test: context.uuid,
+            pending: matches!(kind, Kind::Setup) && !context.running,
+            finished: false,
+        }
+    })
+}
+
+impl Fixture {
+    /// Finishes the fixture as passed.
+    pub fn finish(mut self) {
+        self.end(models::Status::Passed, None);
+    }
+
+    /// Finishes the fixture with an explicit status, e.g. `Broken` for a setup
+    /// that got an `Err`.
+    pub fn finish_with_status(
+        mut self,
+        status: models::Status,
+        status_details: Option<models::StatusDetails>,
+    ) {
+        self.end(status, status_details);
+    }
+
+    fn end(&mut self, status: models::Status, status_details: Option<models::StatusDetails>) {
+        self.finished = true;
+        let pending = with_step(|context, cursor| {
+            if context.uuid != self.test {
+                context.add_misuse(
+                    cursor,
+                    "Fixture finished in another test",
+                    "a fixture started in another test finished in this one, and was ignored",
+                );
+                return None;
+            }
+
+            let node = &mut context.steps[self.id];
+            crate::close_step(&mut node.step, status, status_details);
+            cursor.step = node.parent;
+
+            let step = context.step_with_children(self.id);
+            let fixture = models::FixtureResult {
+                name: step.name,
+                description: step.description,
+                description_html: step.description_html,
+                status: step.status,
+                status_details: step.status_details,
+                stage: step.stage,
+                start: step.start,
+                stop: step.stop,
+                steps: step.steps,
+                attachments: step.attachments,
+                parameters: step.parameters,
+            };
+            match self.kind {
+                Kind::Setup if self.pending => return Some(fixture),
+                Kind::Setup => context.befores.push(fixture),
+                Kind::Teardown => context.afters.push(fixture),
+            }
+            None
+        });
+        if let Some(fixture) = pending {
+            context::add_pending_before(fixture);
+        }
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        if std::thread::panicking() {
+            let (status, details) = with_context(|context| context.unwinding("Fixture panicked"));
+            self.end(status, Some(details));
+        } else {
+            self.end(models::Status::Passed, None);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Support for `async` tests and steps.
+//!
+//! The current test is tracked per thread, but a future may be polled by a
+//! different thread each time it wakes up, e.g. under
+//! `#[tokio::test(flavor = "multi_thread")]`. [`with_current_test`] makes the
+//! test a task-local instead: it is installed on the polling thread for the
+//! duration of every poll. A [`step`] future does the same with its own step,
+//! so steps awaited concurrently, e.g. with `join!`, each keep their own nesting.
+//! A [`flow`] future keeps whatever step its body is in, such as the fixture of
+//! an `async` `#[allure_fixture]`.
+
+use crate::context::{self, Restore, TestHandle};
+use crate::models::Parameter;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Runs `future` with the test that is current on this thread, wherever it is
+/// polled. A test started inside `future` stays with it.
+pub fn with_current_test<F: Future>(future: F) -> WithCurrentTest<F> {
+    WithCurrentTest {
+        handle: context::current(),
+        future: Box::pin(future),
+    }
+}
+
+/// Future returned by [`with_current_test`].
+pub struct WithCurrentTest<F> {
+    handle: Option<TestHandle>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for WithCurrentTest<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = &mut *self;
+        let previous = Restore::enter(this.handle.take());
+        let poll = this.future.as_mut().poll(cx);
+        this.handle = previous.finish();
+        poll
+    }
+}
+
+/// Catches a panic while polling `future`, like [`std::panic::catch_unwind`] does
+/// for closures. The future is assumed to be unwind safe.
+pub fn catch_unwind<F: Future>(future: F) -> CatchUnwind<F> {
+    CatchUnwind {
+        future: Box::pin(future),
+    }
+}
+
+/// Future returned by [`catch_unwind`].
+pub struct CatchUnwind<F> {
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = std::thread::Result<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = self.future.as_mut();
+        match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Ready(value)) => Poll::Ready(Ok(value)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+/// Runs `future` as a step, which starts when it is first polled and finishes
+/// when `future` completes or panics. `#[step]` on an `async fn` and the
+/// `async` form of `allure_step!` build on it.
+pub fn step<F: Future>(name: impl Into<String>, parameters: Vec<Parameter>, future: F) -> Step<F> {
+    Step {
+        start: Some((name.into(), parameters)),
+        handle: None,
+        future: Box::pin(future),
+    }
+}
+
+/// Future returned by [`step`].
+pub struct Step<F> {
+    start: Option<(String, Vec<Parameter>)>,
+    // The test at this step, between polls
+    handle: Option<TestHandle>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Step<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = &mut *self;
+        let previous = match this.start.take() {
+            Some((name, parameters)) => {
+                let previous = Restore::previous(context::current());
+                crate::start_step_with_params(name, parameters);
+                previous
+            }
+            None => Restore::enter(this.handle.take()),
+        };
+
+        let future = this.future.as_mut();
+        match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Pending) => {
+                this.handle = previous.finish();
+                Poll::Pending
+            }
+            Ok(Poll::Ready(value)) => {
+                crate::finish_step(None);
+                Poll::Ready(value)
+            }
+            Err(payload) => {
+                crate::finish_step(Some(payload.as_ref()));
+                panic::resume_unwind(payload);
+            }
+        }
+    }
+}
+
+/// Runs `future` as its own flow of the test that is current when it is first
+/// polled: the innermost step it leaves running between polls, e.g. a fixture it
+/// started, is only current while it is polled. `#[allure_fixture]` on an
+/// `async fn` builds on it.
+pub fn flow<F: Future>(future: F) -> Flow<F> {
+    Flow {
+        started: false,
+        handle: None,
+        future: Box::pin(future),
+    }
+}
+
+/// Future returned by [`flow`].
+pub struct Flow<F> {
+    started: bool,
+    // The test at the innermost step of `future`, between polls
+    handle: Option<TestHandle>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Flow<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = &mut *self;
+        let previous = if this.started {
+            Restore::enter(this.handle.take())
+        } else {
+            this.started = true;
+            Restore::previous(context::current())
+        };
+
+        let poll = this.future.as_mut().poll(cx);
+        if poll.is_pending() {
+            this.handle = previous.finish();
+        }
+        poll
+    }
+}
@@ -0,0 +1,120 @@
+//! Skipped results for `#[ignore]`d tests.
+//!
+//! An ignored test never runs, so it can't report itself. Instead,
+//! `#[allure_test]` registers how to report it as skipped, and when the
+//! `ALLURE_REPORT_IGNORED` environment variable is set, the first test to start
+//! in a test binary reports the ignored tests of the binary that libtest's
+//! filters select. Ignored tests that do run, with `--ignored` or
+//! `--include-ignored`, report themselves as usual.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[doc(hidden)]
+pub use inventory::submit;
+
+/// An ignored test, registered by `#[allure_test]`.
+#[doc(hidden)]
+pub struct IgnoredTest {
+    /// The path of the test as libtest names it, after the crate name.
+    pub path: &'static str,
+    /// Starts the test and ends it as skipped.
+    pub report: fn(),
+}
+
+inventory::collect!(IgnoredTest);
+
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Reports the ignored tests once per process, if requested.
+pub(crate) fn report_once() {
+    if REPORTED.swap(true, Ordering::SeqCst) || !requested() {
+        return;
+    }
+    report_selected(std::env::args().skip(1));
+}
+
+fn requested() -> bool {
+    std::env::var("ALLURE_REPORT_IGNORED")
+        .map(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Reports the ignored tests that the libtest arguments `args` select as
+/// skipped, unless they make libtest run them.
+#[doc(hidden)]
+pub fn report_selected(args: impl IntoIterator<Item = String>) {
+    let filters = Filters::parse(args);
+    if filters.run_ignored {
+        return;
+    }
+    for test in inventory::iter::<IgnoredTest> {
+        let path = test
+            .path
+            .split_once("::")
+            .map_or(test.path, |(_, path)| path);
+        if filters.select(path) {
+            // Not in the middle of the test that happens to start first
+            crate::context::isolated(test.report);
+        }
+    }
+}
+
+// The part of libtest's command line that decides which tests run
+#[derive(Default)]
+struct Filters {
+    filters: Vec<String>,
+    skip: Vec<String>,
+    exact: bool,
+    run_ignored: bool,
+}
+
+// Options of libtest that take a value
+const VALUE_OPTIONS: [&str; 7] = [
+    "--skip",
+    "--test-threads",
+    "--logfile",
+    "--format",
+    "--color",
+    "--shuffle-seed",
+    "-Z",
+];
+
+impl Filters {
+    fn parse(args: impl IntoIterator<Item = String>) -> Self {
+        let mut filters = Filters::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (option, inline_value) = match arg.split_once('=') {
+                Some((option, value)) if option.starts_with('-') => {
+                    (option.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+            match option.as_str() {
+                "--exact" => filters.exact = true,
+                "--ignored" | "--include-ignored" => filters.run_ignored = true,
+                option if VALUE_OPTIONS.contains(&option) => {
+                    let value = inline_value.or_else(|| args.next());
+                    if let ("--skip", Some(value)) = (option, value) {
+                        filters.skip.push(value);
+                    }
+                }
+                option if option.starts_with('-') => {}
+                _ => filters.filters.push(option),
+            }
+        }
+        filters
+    }
+
+    fn select(&self, path: &str) -> bool {
+        let matches = |filter: &String| {
+            if self.exact {
+                path == filter
+            } else {
+                path.contains(filter.as_str())
+            }
+        };
+        (self.filters.is_empty() || self.filters.iter().any(matches))
+            && !self.skip.iter().any(matches)
+    }
+}
@@ -1,160 +1,365 @@
 use chrono::Utc;
-use std::cell::RefCell;
-use std::collections::VecDeque;
+use context::{with_context, with_step};
+use std::any::Any;
+use std::borrow::Borrow;
 use std::thread::Result;
-use uuid::Uuid;
 
+pub mod assertion;
 pub mod attachment;
+mod context;
+pub mod error;
+pub mod fixture;
+pub mod future;
+pub mod ignored;
 pub mod models;
+mod panic_hook;
+pub mod parameter;
+#[cfg(feature = "rayon")]
+pub mod rayon;
+mod suite;
+pub mod thread;
 pub mod writer;
 
+pub use allure_rust_macros::allure_data;
+pub use allure_rust_macros::allure_fixture;
+pub use allure_rust_macros::allure_steps;
 pub use allure_rust_macros::allure_suite;
 pub use allure_rust_macros::allure_test;
 pub use allure_rust_macros::step;
 pub use attachment::{AttachmentType, IntoAttachment};
+pub use context::TestHandle;
+pub use models::Severity;
 pub use serde_json::json;
 
+/// Runs a block as a step and returns its value.
+///
+/// The title may be followed by format arguments:
+/// `allure_step!("Login as {}", user, { ... })`. A block that evaluates to an
+/// `Err` marks the step as broken, like a panic does; its `Result` type must be
+/// known inside the block, e.g. `Ok::<_, MyError>(value)`.
+///
+/// The `async` form returns a future that runs the block as a step when
+/// awaited: `allure_step!("Fetch order", async { ... }).await`.
 #[macro_export]
 macro_rules! allure_step {
+    ($title:expr, async move $body:block) => {
+        allure_rust::future::step($title, Vec::new(), async move $body)
+    };
+    ($title:expr, async $body:block) => {
+        allure_rust::future::step($title, Vec::new(), async $body)
+    };
     ($title:expr, $body:block) => {{
         allure_rust::start_step($title);
 
         let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| $body));
 
-        let step_result: std::thread::Result<()> = match &result {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                let cloned: Box<dyn std::any::Any + Send> =
-                    if let Some(s) = e.downcast_ref::<&'static str>() {
-                        Box::new(*s)
-                    } else if let Some(s) = e.downcast_ref::<String>() {
-                        Box::new(s.clone())
-                    } else {
-                        Box::new("Step failed")
-                    };
-                Err(cloned)
+        match &result {
+            Ok(value) => {
+                use allure_rust::error::{ErrorDetails as _, StepError as _};
+                match (&&allure_rust::error::StepOutput(value)).step_error() {
+                    Some(error) => allure_rust::end_step_with_status(
+                        allure_rust::models::Status::Broken,
+                        Some((&&allure_rust::error::ErrorReport(error)).status_details()),
+                    ),
+                    None => allure_rust::end_step(&result),
+                }
             }
-        };
-        allure_rust::end_step(&step_result);
+            Err(_) => allure_rust::end_step(&result),
+        }
 
-        if result.is_err() {
-            std::panic::resume_unwind(result.unwrap_err());
+        match result {
+            Ok(value) => value,
+            Err(payload) => std::panic::resume_unwind(payload),
         }
     }};
+    // The arguments are collected one token tree at a time up to the body,
+    // which is itself an expression
+    ($title:literal, $($rest:tt)+) => {
+        allure_rust::allure_step!(@format [$title,] $($rest)+)
+    };
+    (@format [$($format:tt)*] async move $body:block) => {
+        allure_rust::allure_step!(format!($($format)*), async move $body)
+    };
+    (@format [$($format:tt)*] async $body:block) => {
+        allure_rust::allure_step!(format!($($format)*), async $body)
+    };
+    (@format [$($format:tt)*] $body:block) => {
+        allure_rust::allure_step!(format!($($format)*), $body)
+    };
+    (@format [$($format:tt)*] $next:tt $($rest:tt)+) => {
+        allure_rust::allure_step!(@format [$($format)* $next] $($rest)+)
+    };
 }
 
-struct TestContext {
-    uuid: Uuid,
-    steps: VecDeque<models::TestStep>,
-    attachments: Vec<models::Attachment>,
-    suite: Option<String>,
-}
-
-impl TestContext {
-    fn new() -> Self {
-        TestContext {
-            uuid: Uuid::new_v4(),
-            steps: VecDeque::new(),
-            attachments: Vec::new(),
-            suite: None,
-        }
-    }
+/// A handle to the test running on the current thread, to record into it from
+/// other threads.
+pub fn current_test() -> TestHandle {
+    context::current_flow()
 }
 
-// Thread-local storage for the test context
-thread_local!(static TEST_CONTEXT: RefCell<TestContext> = RefCell::new(TestContext::new()));
-
 pub fn start_test(#[allow(unused_variables)] name: &'static str) {
-    start_test_with_context(name, None, None);
+    start_test_with_function(name, None, None, None);
 }
 
 pub fn start_test_with_suite(
     #[allow(unused_variables)] name: &'static str,
     suite: Option<&'static str>,
 ) {
-    start_test_with_context(name, suite, None);
+    start_test_with_function(name, suite, None, None);
 }
 
+/// Like [`start_test_with_function`], without the function that identifies the
+/// test.
 pub fn start_test_with_context(
     #[allow(unused_variables)] name: &'static str,
     suite: Option<&'static str>,
     module_path: Option<&'static str>,
 ) {
-    TEST_CONTEXT.with(|ctx| {
-        let mut context = ctx.borrow_mut();
-        *context = TestContext::new();
-
-        if let Some(suite_name) = suite {
-            context.suite = Some(suite_name.to_string());
-        } else if let Some(path) = module_path {
-            let suite_name = path.replace("::", ".");
-            context.suite = Some(suite_name);
-        }
+    start_test_with_function(name, suite, module_path, None);
+}
+
+/// Starts a test on the current thread.
+///
+/// `module_path` and `function` identify the test across runs: together they
+/// form the `fullName` from which `testCaseId` and `historyId` are derived. The
+/// test is grouped in `suite` if given, and by its module path otherwise.
+pub fn start_test_with_function(
+    #[allow(unused_variables)] name: &'static str,
+    suite: Option<&'static str>,
+    module_path: Option<&'static str>,
+    function: Option<&'static str>,
+) {
+    panic_hook::install();
+    ignored::report_once();
+
+    context::start();
+    with_context(|context| {
+        context.module_path = module_path;
+        context.function = function;
+        context.suites = match (suite, module_path) {
+            (Some(suite), _) => suite::named(module_path, suite),
+            (None, Some(path)) => suite::from_module_path(path),
+            (None, None) => Default::default(),
+        };
     });
 }
 
-pub fn end_test(name: &'static str, result: Result<()>) {
-    TEST_CONTEXT.with(|ctx| {
-        let context = ctx.borrow();
-        let stop_time = Utc::now().timestamp_millis();
-        let (status, status_details) = match result {
-            Ok(_) => (models::Status::Passed, None),
-            Err(e) => {
-                let panic_message = if let Some(s) = e.downcast_ref::<&'static str>() {
-                    s.to_string()
-                } else if let Some(s) = e.downcast_ref::<String>() {
-                    s.clone()
-                } else {
-                    "Test panicked".to_string()
-                };
-                (
+/// Finishes the current test, failed if it unwound. Takes the result by value or
+/// by reference, so that the payload of a panic can still be re-raised.
+pub fn end_test(name: &'static str, result: impl Borrow<Result<()>>) {
+    let (status, status_details) = match result.borrow() {
+        Ok(_) => (models::Status::Passed, None),
+        Err(e) => {
+            let (status, details) =
+                with_context(|context| context.failure(e.as_ref(), "Test panicked"));
+            (status, Some(details))
+        }
+    };
+    end_test_with_status(name, status, status_details);
+}
+
+/// Finishes a `#[should_panic]` test: it passes if it panicked with a message
+/// containing `expected` (any panic if there is none).
+pub fn end_test_expecting_panic(name: &'static str, result: &Result<()>, expected: Option<&str>) {
+    let (status, status_details) = match result {
+        Ok(_) => (
+            models::Status::Failed,
+            Some(models::StatusDetails {
+                message: Some("test did not panic as expected".to_string()),
+                trace: None,
+            }),
+        ),
+        Err(e) => {
+            let message = panic_hook::payload_message(e.as_ref()).unwrap_or_default();
+            match expected {
+                Some(expected) if !message.contains(expected) => (
                     models::Status::Failed,
                     Some(models::StatusDetails {
-                        message: Some(panic_message),
-                        trace: None, // You could add trace capturing here
+                        message: Some(format!(
+                            "panic did not contain expected string\n      panic message: {:?}\n expected substring: {:?}",
+                            message, expected
+                        )),
+                        trace: None,
                     }),
-                )
+                ),
+                _ => (models::Status::Passed, None),
             }
-        };
+        }
+    };
+    end_test_with_status(name, status, status_details);
+}
+
+/// Finishes the current test with an explicit status, e.g. `Broken` for a test
+/// that returned `Err`.
+pub fn end_test_with_status(
+    name: &'static str,
+    status: models::Status,
+    status_details: Option<models::StatusDetails>,
+) {
+    // The result is written outside of the lock: writing may panic
+    let (test_result, container) = with_context(|context| {
+        context.running = false;
+        let stop_time = context.start.saturating_add(context.elapsed_millis());
+
+        for node in &mut context.steps {
+            if node.step.stage == "running" {
+                let details = models::StatusDetails {
+                    message: Some("Step did not finish before the test ended".to_string()),
+                    trace: None,
+                };
+                close_step(&mut node.step, models::Status::Broken, Some(details));
+            }
+        }
 
         let mut labels = vec![];
-        if let Some(suite_name) = &context.suite {
+        for (level, suite_name) in suite::LEVELS.iter().zip(&context.suites) {
+            if let Some(suite_name) = suite_name {
+                labels.push(models::Label {
+                    name: level.to_string(),
+                    value: suite_name.clone(),
+                });
+            }
+        }
+        if let Some(path) = context.module_path {
+            labels.push(models::Label {
+                name: "package".to_string(),
+                value: path.replace("::", "."),
+            });
+            // The innermost module plays the part of the class
+            let module = path.rsplit("::").next().unwrap_or(path);
             labels.push(models::Label {
-                name: "suite".to_string(),
-                value: suite_name.clone(),
+                name: "testClass".to_string(),
+                value: module.to_string(),
             });
         }
+        if let Some(function) = context.function {
+            labels.push(models::Label {
+                name: "testMethod".to_string(),
+                value: function.to_string(),
+            });
+        }
+        labels.extend(context.labels.iter().cloned());
+
+        let full_name = match (context.module_path, context.function) {
+            (Some(path), Some(function)) => format!("{}::{}", path, function),
+            (None, Some(function)) => function.to_string(),
+            _ => name.to_string(),
+        };
+        let parameters = context.parameters.clone();
+        let container = fixture_container(context, name, stop_time);
 
         let test_result = models::TestResult {
             uuid: context.uuid,
-            history_id: Uuid::new_v4(),
+            history_id: history_id(&full_name, &parameters),
+            test_case_id: test_case_id(&full_name),
             name: name.to_string(),
-            description: None,
+            full_name,
+            description: context.description.clone(),
+            description_html: render_description_html(
+                &context.description,
+                &context.description_html,
+            ),
             status,
             status_details,
             stage: "finished".to_string(),
-            start: stop_time - 1,
+            start: context.start,
             stop: stop_time,
             labels,
-            parameters: vec![],
-            links: vec![],
-            steps: context.steps.clone().into_iter().collect(),
+            parameters,
+            links: context.links.clone(),
+            steps: context.step_tree(),
             attachments: context.attachments.clone(),
         };
-
-        writer::write_test_result(&test_result);
+        (test_result, container)
     });
+    writer::write_test_result(&test_result);
+    if let Some(container) = container {
+        writer::write_container(&container);
+    }
+}
+
+// The fixtures the test ran, in a container of their own with the test as its
+// only child
+fn fixture_container(
+    context: &mut context::TestContext,
+    name: &str,
+    stop_time: i64,
+) -> Option<models::TestResultContainer> {
+    let befores = std::mem::take(&mut context.befores);
+    let afters = std::mem::take(&mut context.afters);
+    if befores.is_empty() && afters.is_empty() {
+        return None;
+    }
+
+    let fixtures = || befores.iter().chain(&afters);
+    let start = fixtures().map(|fixture| fixture.start).min();
+    let stop = fixtures().map(|fixture| fixture.stop).max();
+    Some(models::TestResultContainer {
+        uuid: uuid::Uuid::new_v4(),
+        name: Some(name.to_string()),
+        children: vec![context.uuid],
+        start: start.map_or(context.start, |start| start.min(context.start)),
+        stop: stop.map_or(stop_time, |stop| stop.max(stop_time)),
+        befores,
+        afters,
+    })
 }
 
-pub fn start_step(name: &'static str) {
+// An explicit HTML description always wins; with the `markdown` feature the
+// Markdown description is rendered otherwise
+fn render_description_html(
+    description: &Option<String>,
+    description_html: &Option<String>,
+) -> Option<String> {
+    if description_html.is_some() {
+        return description_html.clone();
+    }
+
+    #[cfg(feature = "markdown")]
+    {
+        description.as_deref().map(|markdown| {
+            let mut html = String::new();
+            pulldown_cmark::html::push_html(&mut html, pulldown_cmark::Parser::new(markdown));
+            html
+        })
+    }
+
+    #[cfg(not(feature = "markdown"))]
+    {
+        let _ = description;
+        None
+    }
+}
+
+fn test_case_id(full_name: &str) -> String {
+    format!("{:x}", md5::compute(full_name))
+}
+
+// Parameters are sorted so the id does not depend on the order they were added in
+fn history_id(full_name: &str, parameters: &[models::Parameter]) -> String {
+    let mut params: Vec<_> = parameters
+        .iter()
+        .filter(|p| !p.excluded)
+        .map(|p| format!("{}={}", p.name, p.value))
+        .collect();
+    params.sort();
+    format!(
+        "{:x}",
+        md5::compute(format!("{}:{}", full_name, params.join(";")))
+    )
+}
+
+pub fn start_step(name: impl Into<String>) {
     start_step_with_params(name, Vec::new());
 }
 
-pub fn start_step_with_params(name: &'static str, parameters: Vec<models::Parameter>) {
-    TEST_CONTEXT.with(|ctx| {
-        let mut context = ctx.borrow_mut();
+pub fn start_step_with_params(name: impl Into<String>, parameters: Vec<models::Parameter>) {
+    let name = name.into();
+    with_step(|context, cursor| {
         let new_step = models::TestStep {
-            name: name.to_string(),
+            name,
+            description: None,
+            description_html: None,
             status: models::Status::Passed,
             status_details: None,
             stage: "running".to_string(),
@@ -164,46 +369,67 @@ pub fn start_step_with_params(name: &'static str, parameters: Vec<models::Parame
             attachments: Vec::new(),
             parameters,
         };
-        context.steps.push_back(new_step);
+        cursor.step = Some(context.add_step(cursor, new_step));
     });
 }
 
-pub fn end_step(result: &Result<()>) {
-    TEST_CONTEXT.with(|ctx| {
-        let mut context = ctx.borrow_mut();
-        if let Some(mut step) = context.steps.pop_back() {
-            step.stop = Utc::now().timestamp_millis();
-            step.stage = "finished".to_string();
-            if let Err(e) = result {
-                step.status = models::Status::Failed;
-                let panic_message = if let Some(s) = e.downcast_ref::<&'static str>() {
-                    s.to_string()
-                } else if let Some(s) = e.downcast_ref::<String>() {
-                    s.clone()
-                } else {
-                    "Step panicked".to_string()
-                };
-                step.status_details = Some(models::StatusDetails {
-                    message: Some(panic_message),
-                    trace: None,
-                });
-            }
+/// Finishes the innermost running step, failed if `result` holds the payload
+/// of a panic.
+pub fn end_step<T>(result: &Result<T>) {
+    finish_step(result.as_ref().err().map(|e| e.as_ref()));
+}
 
-            if step.stage == "finished" && context.steps.is_empty() {
-                context.steps.push_front(step);
-            } else if let Some(parent_step) = context.steps.back_mut() {
-                if parent_step.stage == "running" {
-                    parent_step.steps.push(step);
-                } else {
-                    context.steps.push_front(step);
-                }
-            } else {
-                context.steps.push_front(step);
-            }
+/// Finishes the innermost running step with an explicit status, e.g. `Broken`
+/// for a step that returned `Err`.
+pub fn end_step_with_status(status: models::Status, status_details: Option<models::StatusDetails>) {
+    finish_step_with(|_| (status, status_details));
+}
+
+// Finishes the innermost step of this flow, failed if it unwound with `payload`
+fn finish_step(payload: Option<&(dyn Any + Send)>) {
+    finish_step_with(|context| match payload {
+        Some(payload) => {
+            let (status, details) = context.failure(payload, "Step panicked");
+            (status, Some(details))
         }
+        None => (models::Status::Passed, None),
     });
 }
 
+fn finish_step_with(
+    outcome: impl FnOnce(&context::TestContext) -> (models::Status, Option<models::StatusDetails>),
+) {
+    with_step(|context, cursor| {
+        let Some(id) = cursor.step.filter(|&id| Some(id) != cursor.base) else {
+            context.add_misuse(
+                cursor,
+                "Unbalanced end_step()",
+                "end_step() was called without a matching start_step() on this thread, and ignored",
+            );
+            return;
+        };
+        let (status, status_details) = outcome(context);
+
+        let node = &mut context.steps[id];
+        close_step(&mut node.step, status, status_details);
+        cursor.step = node.parent;
+    });
+}
+
+fn close_step(
+    step: &mut models::TestStep,
+    status: models::Status,
+    status_details: Option<models::StatusDetails>,
+) {
+    step.stop = Utc::now().timestamp_millis();
+    step.stage = "finished".to_string();
+    step.description_html = render_description_html(&step.description, &step.description_html);
+    step.status = status;
+    step.status_details = status_details;
+}
+
+/// Attaches `content` to the innermost running step, or to the current test
+/// outside of steps.
 pub fn add_attachment<T: IntoAttachment>(name: impl Into<String>, content: T) {
     let attachment_type = content.attachment_type();
     let bytes = content.into_bytes();
@@ -213,9 +439,10 @@ pub fn add_attachment<T: IntoAttachment>(name: impl Into<String>, content: T) {
         source,
         attachment_type: attachment_type.mime_type().to_string(),
     };
-    TEST_CONTEXT.with(|ctx| ctx.borrow_mut().attachments.push(attachment));
+    attach(attachment);
 }
 
+/// Like [`add_attachment`], with an explicit type.
 pub fn add_attachment_with_type<T: IntoAttachment>(
     name: impl Into<String>,
     content: T,
@@ -228,5 +455,141 @@ pub fn add_attachment_with_type<T: IntoAttachment>(
         source,
         attachment_type: attachment_type.mime_type().to_string(),
     };
-    TEST_CONTEXT.with(|ctx| ctx.borrow_mut().attachments.push(attachment));
+    attach(attachment);
+}
+
+// Attachments belong to the innermost running step, or to the test outside of steps
+fn attach(attachment: models::Attachment) {
+    with_step(|context, cursor| match cursor.step {
+        Some(id) => context.steps[id].step.attachments.push(attachment),
+        None => context.attachments.push(attachment),
+    });
+}
+
+/// Adds a label to the current test.
+pub fn label(name: impl Into<String>, value: impl Into<String>) {
+    let label = models::Label {
+        name: name.into(),
+        value: value.into(),
+    };
+    with_context(|context| context.labels.push(label));
+}
+
+pub fn epic(value: impl Into<String>) {
+    label("epic", value);
+}
+
+pub fn feature(value: impl Into<String>) {
+    label("feature", value);
+}
+
+pub fn story(value: impl Into<String>) {
+    label("story", value);
+}
+
+pub fn owner(value: impl Into<String>) {
+    label("owner", value);
+}
+
+pub fn tag(value: impl Into<String>) {
+    label("tag", value);
+}
+
+pub fn severity(level: Severity) {
+    label("severity", level.as_str());
+}
+
+/// Replaces the parent suite the current test got from its module path.
+pub fn parent_suite(name: impl Into<String>) {
+    set_suite(0, name.into());
+}
+
+/// Replaces the suite the current test got from its module path.
+pub fn suite(name: impl Into<String>) {
+    set_suite(1, name.into());
+}
+
+/// Replaces the sub-suite the current test got from its module path.
+pub fn sub_suite(name: impl Into<String>) {
+    set_suite(2, name.into());
+}
+
+fn set_suite(level: usize, name: String) {
+    with_context(|context| context.suites[level] = Some(name));
+}
+
+/// Adds a link to the current test.
+pub fn link(name: impl Into<String>, url: impl Into<String>) {
+    add_link(name.into(), url.into(), "link");
+}
+
+/// Links the current test to an issue.
+///
+/// The URL is built from the `ALLURE_LINK_ISSUE_PATTERN` environment variable,
+/// where `{}` is replaced with the issue id, e.g.
+/// `https://jira.example.com/browse/{}`. Without a pattern the id is used as is.
+pub fn issue(id: impl Into<String>) {
+    let id = id.into();
+    add_link(id.clone(), link_url("ISSUE", &id), "issue");
+}
+
+/// Links the current test to a test management system case.
+///
+/// The URL is built from the `ALLURE_LINK_TMS_PATTERN` environment variable,
+/// the same way as for [`issue`].
+pub fn tms(id: impl Into<String>) {
+    let id = id.into();
+    add_link(id.clone(), link_url("TMS", &id), "tms");
+}
+
+fn add_link(name: String, url: String, link_type: &str) {
+    let link = models::Link {
+        name,
+        url,
+        link_type: link_type.to_string(),
+    };
+    with_context(|context| context.links.push(link));
+}
+
+fn link_url(kind: &str, id: &str) -> String {
+    std::env::var(format!("ALLURE_LINK_{}_PATTERN", kind))
+        .map(|pattern| pattern.replace("{}", id))
+        .unwrap_or_else(|_| id.to_string())
+}
+
+/// Sets the Markdown description of the current test.
+pub fn description(text: impl Into<String>) {
+    let text = text.into();
+    with_context(|context| context.description = Some(text));
+}
+
+/// Sets the HTML description of the current test.
+pub fn description_html(html: impl Into<String>) {
+    let html = html.into();
+    with_context(|context| context.description_html = Some(html));
+}
+
+/// Sets the Markdown description of the innermost running step.
+pub fn step_description(text: impl Into<String>) {
+    let text = text.into();
+    with_step(|context, cursor| {
+        if let Some(id) = cursor.step {
+            context.steps[id].step.description = Some(text);
+        }
+    });
+}
+
+/// Adds a parameter to the innermost running step, or to the current test
+/// outside of steps. Parameters of the test take part in `historyId`.
+pub fn parameter(name: impl Into<String>, value: impl ToString) {
+    let parameter = models::Parameter {
+        name: name.into(),
+        value: value.to_string(),
+        mode: models::ParameterMode::Default,
+        excluded: false,
+    };
+    with_step(|context, cursor| match cursor.step {
+        Some(id) => context.steps[id].step.parameters.push(parameter),
+        None => context.parameters.push(parameter),
+    });
 }
@@ -10,6 +10,28 @@ pub enum Status {
     Skipped,
 }
 
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Blocker,
+    Critical,
+    Normal,
+    Minor,
+    Trivial,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Blocker => "blocker",
+            Severity::Critical => "critical",
+            Severity::Normal => "normal",
+            Severity::Minor => "minor",
+            Severity::Trivial => "trivial",
+        }
+    }
+}
+
 #[derive(Serialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct StatusDetails {
@@ -32,6 +54,10 @@ pub struct Attachment {
 #[serde(rename_all = "camelCase")]
 pub struct TestStep {
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_html: Option<String>,
     pub status: Status,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status_details: Option<StatusDetails>,
@@ -50,6 +76,29 @@ pub struct TestStep {
 pub struct Parameter {
     pub name: String,
     pub value: String,
+    #[serde(skip_serializing_if = "ParameterMode::is_default")]
+    pub mode: ParameterMode,
+    /// Leaves the parameter out of the `historyId` of the test.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub excluded: bool,
+}
+
+/// How the report shows a parameter.
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ParameterMode {
+    #[default]
+    Default,
+    /// The value is shown as `******`.
+    Masked,
+    /// The parameter is not shown at all.
+    Hidden,
+}
+
+impl ParameterMode {
+    fn is_default(&self) -> bool {
+        *self == ParameterMode::Default
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -57,11 +106,14 @@ pub struct Parameter {
 pub struct TestResult {
     #[serde(default = "Uuid::new_v4")]
     pub uuid: Uuid,
-    #[serde(default = "Uuid::new_v4")]
-    pub history_id: Uuid,
+    pub history_id: String,
+    pub test_case_id: String,
     pub name: String,
+    pub full_name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_html: Option<String>,
     pub status: Status,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status_details: Option<StatusDetails>,
@@ -93,3 +145,42 @@ pub struct Link {
     #[serde(rename = "type")]
     pub link_type: String,
 }
+
+/// A setup or teardown that ran for the tests of a container.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FixtureResult {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_html: Option<String>,
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_details: Option<StatusDetails>,
+    pub stage: String,
+    pub start: i64,
+    pub stop: i64,
+    #[serde(default)]
+    pub steps: Vec<TestStep>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    #[serde(default)]
+    pub parameters: Vec<Parameter>,
+}
+
+/// The fixtures of a group of tests, linked to them by their uuids.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TestResultContainer {
+    pub uuid: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub children: Vec<Uuid>,
+    #[serde(default)]
+    pub befores: Vec<FixtureResult>,
+    #[serde(default)]
+    pub afters: Vec<FixtureResult>,
+    pub start: i64,
+    pub stop: i64,
+}
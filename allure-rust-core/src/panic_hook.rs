@@ -0,0 +1,167 @@
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::panic::{self, PanicHookInfo};
+use std::sync::Once;
+
+/// Why a test or step panicked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PanicKind {
+    /// An `assert!`-family macro failed: the product misbehaved.
+    Assertion,
+    /// Anything else (`unwrap` on `None`, out of bounds indexing, explicit `panic!`, ...):
+    /// the test itself is broken.
+    Other,
+}
+
+/// What the panic hook saw when the current thread last panicked.
+#[derive(Debug)]
+pub(crate) struct PanicRecord {
+    pub message: String,
+    pub location: Option<String>,
+    pub kind: PanicKind,
+    // Symbols are only resolved when the record ends up in a report
+    backtrace: Backtrace,
+}
+
+// Frames of the runtime, the standard library and the test harness are not
+// interesting in a report
+const HIDDEN_FRAME_PREFIXES: [&str; 7] = [
+    "std::",
+    "core::",
+    "alloc::",
+    "test::",
+    "panic_unwind::",
+    "allure_rust_core::",
+    "__rust",
+];
+
+static INSTALL: Once = Once::new();
+
+thread_local! {
+    // Set while an assertion of `crate::assertion` panics
+    static ASSERTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Installs the hook once per process. The previously installed hook still runs,
+/// so the usual panic output is printed as before.
+pub(crate) fn install() {
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            crate::context::record_panic(|| PanicRecord::from_hook_info(info));
+            previous(info);
+        }));
+    });
+}
+
+impl PanicRecord {
+    fn from_hook_info(info: &PanicHookInfo<'_>) -> Self {
+        let message = payload_message(info.payload()).unwrap_or_else(|| "Box<dyn Any>".to_string());
+        let kind = if ASSERTING.try_with(Cell::get).unwrap_or(false) {
+            PanicKind::Assertion
+        } else {
+            classify(&message)
+        };
+        PanicRecord {
+            message,
+            location: info.location().map(|l| l.to_string()),
+            kind,
+            backtrace: Backtrace::force_capture(),
+        }
+    }
+
+    /// The panic location followed by the backtrace frames of the test code.
+    pub fn trace(&self) -> String {
+        let mut trace = match &self.location {
+            Some(location) => format!("panicked at {}", location),
+            None => "panicked".to_string(),
+        };
+        for frame in trim_frames(&self.backtrace.to_string()) {
+            trace.push('\n');
+            trace.push_str(&frame);
+        }
+        trace
+    }
+}
+
+// Splits a formatted backtrace into frames ("N: symbol" plus its "at file:line"
+// lines) and keeps those between the panic machinery and the test harness, the same
+// part that `RUST_BACKTRACE=1` prints
+fn trim_frames(backtrace: &str) -> Vec<String> {
+    let mut frames: Vec<(String, String)> = Vec::new();
+    for line in backtrace.lines() {
+        let trimmed = line.trim_start();
+        let symbol = trimmed
+            .split_once(": ")
+            .filter(|(index, _)| index.chars().all(|c| c.is_ascii_digit()))
+            .map(|(_, symbol)| symbol);
+        match (symbol, frames.last_mut()) {
+            (Some(symbol), _) => frames.push((symbol.to_string(), line.to_string())),
+            (None, Some((_, frame))) => {
+                frame.push('\n');
+                frame.push_str(line);
+            }
+            (None, None) => {}
+        }
+    }
+
+    let start = frames
+        .iter()
+        .rposition(|(symbol, _)| symbol.contains("__rust_end_short_backtrace"))
+        .map_or(0, |index| index + 1);
+    let end = frames
+        .iter()
+        .position(|(symbol, _)| symbol.contains("__rust_begin_short_backtrace"))
+        .unwrap_or(frames.len())
+        .max(start);
+
+    frames[start..end]
+        .iter()
+        .filter(|(symbol, _)| !is_hidden_frame(symbol))
+        .map(|(_, frame)| frame.clone())
+        .collect()
+}
+
+fn is_hidden_frame(symbol: &str) -> bool {
+    // Trait impls look like `<F as core::ops::function::FnOnce<()>>::call_once`
+    let path = symbol.trim_start_matches('<');
+    HIDDEN_FRAME_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+        || symbol.contains(" as core::ops::function::Fn")
+}
+
+pub(crate) fn payload_message(payload: &(dyn Any + Send)) -> Option<String> {
+    if let Some(s) = payload.downcast_ref::<&'static str>() {
+        Some(s.to_string())
+    } else {
+        payload.downcast_ref::<String>().cloned()
+    }
+}
+
+/// Runs `f`, taking a panic in it for a failed assertion.
+pub(crate) fn asserting(f: impl FnOnce()) {
+    struct Reset;
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            ASSERTING.with(|asserting| asserting.set(false));
+        }
+    }
+
+    ASSERTING.with(|asserting| asserting.set(true));
+    let _reset = Reset;
+    f();
+}
+
+/// Classifies a panic that was not marked by [`asserting`] by its message: those
+/// of the `std` assertions without a custom message are recognized.
+pub(crate) fn classify(message: &str) -> PanicKind {
+    // Messages of `assert!(cond)`, `assert_eq!` and `assert_ne!`
+    if message.starts_with("assertion failed") || message.starts_with("assertion `left") {
+        PanicKind::Assertion
+    } else {
+        PanicKind::Other
+    }
+}
@@ -0,0 +1,27 @@
+//! Values of the arguments `#[step]` records as parameters.
+//!
+//! The macro calls `(&&ArgumentValue(&argument)).argument_value()`, and method
+//! resolution picks the `Debug` of the argument when it has one, or else the
+//! name of its type, so arguments that are not `Debug` don't stop a function
+//! from being a step.
+
+use std::any::type_name;
+use std::fmt::Debug;
+
+pub struct ArgumentValue<'a, T: ?Sized>(pub &'a T);
+
+pub trait FormatArgument {
+    fn argument_value(&self) -> String;
+}
+
+impl<T: Debug + ?Sized> FormatArgument for &ArgumentValue<'_, T> {
+    fn argument_value(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+impl<T: ?Sized> FormatArgument for ArgumentValue<'_, T> {
+    fn argument_value(&self) -> String {
+        type_name::<T>().to_string()
+    }
+}
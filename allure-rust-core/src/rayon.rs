@@ -0,0 +1,276 @@
+//! Steps from rayon jobs, with the `rayon` feature.
+//!
+//! Rayon runs jobs on its own thread pool, where no test is current. The
+//! adapters here carry the current test into the jobs, so their steps nest in
+//! the step that was running when the work was forked. Steps of concurrent jobs
+//! are ordered by the index of the job, not by when the job happened to run.
+//!
+//! Only work started through these adapters is tracked: a plain `par_iter()` or
+//! `rayon::join` runs without a current test, and its steps are lost.
+
+use crate::TestHandle;
+use crate::context::{self, Restore};
+use ::rayon::iter::plumbing::{Consumer, Folder, Producer, ProducerCallback, UnindexedConsumer};
+use ::rayon::iter::{IndexedParallelIterator, ParallelIterator};
+
+/// Like [`rayon::join`], with the current test carried into both closures.
+pub fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    let test = crate::current_test();
+    let fork = test.fork();
+    let (test_a, test_b) = (test.branch(fork, 0), test.branch(fork, 1));
+    ::rayon::join(move || test_a.run(a), move || test_b.run(b))
+}
+
+/// Adds [`with_current_test`](ParallelIteratorExt::with_current_test) to
+/// indexed parallel iterators.
+pub trait ParallelIteratorExt: IndexedParallelIterator {
+    /// Runs the rest of the pipeline (`map`, `for_each`, ...) in the current
+    /// test, with the steps of each item ordered by its index. The result is
+    /// indexed itself, so `enumerate`, `zip`, `collect_into_vec` and the like
+    /// can follow.
+    fn with_current_test(self) -> WithCurrentTest<Self> {
+        WithCurrentTest {
+            base: self,
+            test: crate::current_test(),
+        }
+    }
+}
+
+impl<I: IndexedParallelIterator> ParallelIteratorExt for I {}
+
+/// Parallel iterator returned by
+/// [`with_current_test`](ParallelIteratorExt::with_current_test).
+pub struct WithCurrentTest<I> {
+    base: I,
+    test: TestHandle,
+}
+
+impl<I: IndexedParallelIterator> ParallelIterator for WithCurrentTest<I> {
+    type Item = I::Item;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let fork = self.test.fork();
+        self.base.enumerate().drive_unindexed(TestConsumer {
+            base: consumer,
+            test: self.test,
+            fork,
+        })
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.base.len())
+    }
+}
+
+impl<I: IndexedParallelIterator> IndexedParallelIterator for WithCurrentTest<I> {
+    fn drive<C: Consumer<Self::Item>>(self, consumer: C) -> C::Result {
+        let fork = self.test.fork();
+        self.base.enumerate().drive(TestConsumer {
+            base: consumer,
+            test: self.test,
+            fork,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.base.len()
+    }
+
+    fn with_producer<CB: ProducerCallback<Self::Item>>(self, callback: CB) -> CB::Output {
+        let fork = self.test.fork();
+        self.base.enumerate().with_producer(TestCallback {
+            base: callback,
+            test: self.test,
+            fork,
+        })
+    }
+}
+
+// Consumes `(index, item)` and passes the item on inside branch `index`
+struct TestConsumer<C> {
+    base: C,
+    test: TestHandle,
+    fork: u64,
+}
+
+impl<C> TestConsumer<C> {
+    fn with_base<D>(&self, base: D) -> TestConsumer<D> {
+        TestConsumer {
+            base,
+            test: self.test.clone(),
+            fork: self.fork,
+        }
+    }
+}
+
+impl<T, C: Consumer<T>> Consumer<(usize, T)> for TestConsumer<C> {
+    type Folder = TestConsumer<C::Folder>;
+    type Reducer = C::Reducer;
+    type Result = C::Result;
+
+    fn split_at(self, index: usize) -> (Self, Self, Self::Reducer) {
+        let consumer = self.with_base(());
+        let (left, right, reducer) = self.base.split_at(index);
+        (consumer.with_base(left), consumer.with_base(right), reducer)
+    }
+
+    fn into_folder(self) -> Self::Folder {
+        let consumer = self.with_base(());
+        consumer.with_base(self.base.into_folder())
+    }
+
+    fn full(&self) -> bool {
+        self.base.full()
+    }
+}
+
+impl<T, C: UnindexedConsumer<T>> UnindexedConsumer<(usize, T)> for TestConsumer<C> {
+    fn split_off_left(&self) -> Self {
+        self.with_base(self.base.split_off_left())
+    }
+
+    fn to_reducer(&self) -> Self::Reducer {
+        self.base.to_reducer()
+    }
+}
+
+impl<T, F: Folder<T>> Folder<(usize, T)> for TestConsumer<F> {
+    type Result = F::Result;
+
+    fn consume(self, (index, item): (usize, T)) -> Self {
+        let folder = self.with_base(());
+        let branch = self.test.branch(self.fork, index);
+        folder.with_base(branch.run(|| self.base.consume(item)))
+    }
+
+    fn complete(self) -> F::Result {
+        self.base.complete()
+    }
+
+    fn full(&self) -> bool {
+        self.base.full()
+    }
+}
+
+// Hands the producer of `(index, item)` on as a producer of items in branch `index`
+struct TestCallback<CB> {
+    base: CB,
+    test: TestHandle,
+    fork: u64,
+}
+
+impl<T, CB: ProducerCallback<T>> ProducerCallback<(usize, T)> for TestCallback<CB> {
+    type Output = CB::Output;
+
+    fn callback<P: Producer<Item = (usize, T)>>(self, base: P) -> CB::Output {
+        self.base.callback(TestProducer {
+            base,
+            test: self.test,
+            fork: self.fork,
+        })
+    }
+}
+
+// Adapters like `zip` iterate the producer rather than fold into it, so the
+// branch is switched by the iterator instead
+struct TestProducer<P> {
+    base: P,
+    test: TestHandle,
+    fork: u64,
+}
+
+impl<P> TestProducer<P> {
+    fn with_base<Q>(&self, base: Q) -> TestProducer<Q> {
+        TestProducer {
+            base,
+            test: self.test.clone(),
+            fork: self.fork,
+        }
+    }
+}
+
+impl<T, P: Producer<Item = (usize, T)>> Producer for TestProducer<P> {
+    type Item = T;
+    type IntoIter = TestIter<P::IntoIter>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        TestIter {
+            base: self.base.into_iter(),
+            test: self.test,
+            fork: self.fork,
+            restore: None,
+        }
+    }
+
+    fn min_len(&self) -> usize {
+        self.base.min_len()
+    }
+
+    fn max_len(&self) -> usize {
+        self.base.max_len()
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let producer = self.with_base(());
+        let (left, right) = self.base.split_at(index);
+        (producer.with_base(left), producer.with_base(right))
+    }
+}
+
+// Makes branch `index` current from when its item is taken until the next one
+// is, which covers the work done on the item; the thread's own handle is put
+// back once the iterator is exhausted or dropped
+struct TestIter<I> {
+    base: I,
+    test: TestHandle,
+    fork: u64,
+    restore: Option<Restore>,
+}
+
+impl<I> TestIter<I> {
+    fn enter<T>(&mut self, next: Option<(usize, T)>) -> Option<T> {
+        let Some((index, item)) = next else {
+            self.restore = None;
+            return None;
+        };
+        let branch = self.test.branch(self.fork, index);
+        match self.restore {
+            Some(_) => {
+                context::replace_current(Some(branch));
+            }
+            None => self.restore = Some(Restore::enter(Some(branch))),
+        }
+        Some(item)
+    }
+}
+
+impl<T, I: Iterator<Item = (usize, T)>> Iterator for TestIter<I> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let next = self.base.next();
+        self.enter(next)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.base.size_hint()
+    }
+}
+
+impl<T, I: DoubleEndedIterator<Item = (usize, T)>> DoubleEndedIterator for TestIter<I> {
+    fn next_back(&mut self) -> Option<T> {
+        let next = self.base.next_back();
+        self.enter(next)
+    }
+}
+
+impl<T, I: ExactSizeIterator<Item = (usize, T)>> ExactSizeIterator for TestIter<I> {}
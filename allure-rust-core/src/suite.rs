@@ -0,0 +1,69 @@
+//! The suites a test is grouped in.
+//!
+//! Allure has three levels of suites. By default each segment of the module
+//! path goes to the next level and the last level takes the rest, so
+//! `shop::orders::refunds::tests` becomes the parent suite `shop`, the suite
+//! `orders` and the sub-suite `refunds.tests`. `ALLURE_SUITE_MAPPING` lists the
+//! level of each segment instead, with `-` to leave a segment out, e.g.
+//! `-,suite,subSuite` drops the crate name. It is read once per process.
+
+use std::sync::OnceLock;
+
+pub(crate) const LEVELS: [&str; 3] = ["parentSuite", "suite", "subSuite"];
+
+/// The parent suite, suite and sub-suite of a test.
+pub(crate) type Suites = [Option<String>; 3];
+
+const DEFAULT_MAPPING: [Option<usize>; 3] = [Some(0), Some(1), Some(2)];
+
+/// The suites of a test in module `path`.
+pub(crate) fn from_module_path(path: &str) -> Suites {
+    let mapping = mapping();
+    let mut suites = Suites::default();
+    for (index, segment) in path.split("::").enumerate() {
+        let Some(level) = mapping[index.min(mapping.len() - 1)] else {
+            continue;
+        };
+        match &mut suites[level] {
+            Some(suite) => {
+                suite.push('.');
+                suite.push_str(segment);
+            }
+            slot => *slot = Some(segment.to_string()),
+        }
+    }
+    suites
+}
+
+/// The suites of a test in the explicitly named suite `name`: the parent suite
+/// is still its crate.
+pub(crate) fn named(path: Option<&str>, name: &str) -> Suites {
+    let crate_name = path.and_then(|path| path.split("::").next());
+    [crate_name.map(str::to_string), Some(name.to_string()), None]
+}
+
+fn mapping() -> &'static [Option<usize>] {
+    static MAPPING: OnceLock<Vec<Option<usize>>> = OnceLock::new();
+    MAPPING.get_or_init(parse_mapping)
+}
+
+// An invalid value is reported once, since it is only parsed once
+fn parse_mapping() -> Vec<Option<usize>> {
+    let Ok(value) = std::env::var("ALLURE_SUITE_MAPPING") else {
+        return DEFAULT_MAPPING.to_vec();
+    };
+    let mapping: Option<Vec<_>> = value
+        .split(',')
+        .map(|level| match level.trim() {
+            "-" => Some(None),
+            level => LEVELS.iter().position(|name| *name == level).map(Some),
+        })
+        .collect();
+    mapping.unwrap_or_else(|| {
+        eprintln!(
+            "allure-rust: invalid ALLURE_SUITE_MAPPING {:?}, expected a list of parentSuite, suite, subSuite or -",
+            value
+        );
+        DEFAULT_MAPPING.to_vec()
+    })
+}
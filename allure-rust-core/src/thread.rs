@@ -0,0 +1,30 @@
+//! Threads that record into the test that spawned them.
+//!
+//! Steps, attachments and metadata from these threads land in the spawning
+//! test, with steps nested in the step that was running when the thread was
+//! spawned.
+
+use std::thread::{JoinHandle, Scope, ScopedJoinHandle};
+
+/// Like [`std::thread::spawn`], with the current test carried into the new thread.
+pub fn spawn<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let test = crate::current_test();
+    std::thread::spawn(move || test.run(f))
+}
+
+/// Like [`Scope::spawn`], with the current test carried into the new thread.
+pub fn spawn_scoped<'scope, F, T>(
+    scope: &'scope Scope<'scope, '_>,
+    f: F,
+) -> ScopedJoinHandle<'scope, T>
+where
+    F: FnOnce() -> T + Send + 'scope,
+    T: Send + 'scope,
+{
+    let test = crate::current_test();
+    scope.spawn(move || test.run(f))
+}
@@ -1,4 +1,4 @@
-use crate::models::TestResult;
+use crate::models::{TestResult, TestResultContainer};
 use std::env;
 use std::fs;
 use std::path::PathBuf;
@@ -22,6 +22,17 @@ pub fn write_test_result(test_result: &TestResult) {
     fs::write(filename, json).expect("Failed to write TestResult to file");
 }
 
+pub fn write_container(container: &TestResultContainer) {
+    let dir = get_results_dir();
+    if !dir.exists() {
+        fs::create_dir_all(&dir).expect("Failed to create allure-results directory");
+    }
+    let filename = dir.join(format!("{}-container.json", container.uuid));
+    let json =
+        serde_json::to_string_pretty(container).expect("Failed to serialize TestResultContainer");
+    fs::write(filename, json).expect("Failed to write TestResultContainer to file");
+}
+
 pub fn write_attachment(source: &[u8], extension: &str) -> String {
     let dir = get_results_dir();
     if !dir.exists() {
@@ -0,0 +1,312 @@
+use proc_macro2::TokenStream;
+use quote::{ToTokens, quote};
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Ident, LitStr, Token, parenthesized};
+
+const TEST_KEYS: &str =
+    "title, epic, feature, story, owner, severity, tag, tags, link, issue, tms, description";
+
+const STEP_KEYS: &str = "title, skip, skip_all, mask, hide, exclude, display, debug";
+
+const FIXTURE_KEYS: &str = "title, setup, teardown";
+
+const SEVERITIES: [(&str, &str); 5] = [
+    ("blocker", "Blocker"),
+    ("critical", "Critical"),
+    ("normal", "Normal"),
+    ("minor", "Minor"),
+    ("trivial", "Trivial"),
+];
+
+/// Arguments of `#[allure_test(...)]`.
+///
+/// Accepts either a bare title literal or `key = value` pairs, e.g.
+/// `#[allure_test(title = "Refund", epic = "Billing", severity = critical, tags("smoke"))]`.
+#[derive(Default)]
+pub(crate) struct TestArgs {
+    pub title: Option<LitStr>,
+    /// Runtime calls that attach the static metadata to the running test.
+    pub metadata: Vec<Metadata>,
+}
+
+/// One runtime call of [`TestArgs::metadata`], with the key it came from.
+#[derive(Clone)]
+pub(crate) struct Metadata {
+    pub key: Ident,
+    pub call: TokenStream,
+}
+
+impl ToTokens for Metadata {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.call.to_tokens(tokens);
+    }
+}
+
+// A test can only have one of these, so its own replaces the one of its suite
+const SINGLE_KEYS: [&str; 3] = ["owner", "severity", "description"];
+
+/// The metadata of a test or suite after the `inherited` metadata of the
+/// suites around it.
+pub(crate) fn inherit(inherited: &[Metadata], own: &[Metadata]) -> Vec<Metadata> {
+    let overridden = |key: &Ident| {
+        SINGLE_KEYS.iter().any(|single| key == single) && own.iter().any(|m| m.key == *key)
+    };
+    inherited
+        .iter()
+        .filter(|metadata| !overridden(&metadata.key))
+        .chain(own)
+        .cloned()
+        .collect()
+}
+
+impl Parse for TestArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = TestArgs::default();
+
+        if input.peek(LitStr) {
+            args.title = Some(input.parse()?);
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            match key.to_string().as_str() {
+                "title" => {
+                    let title = parse_value(input)?;
+                    if args.title.is_some() {
+                        return Err(syn::Error::new(key.span(), "duplicate test title"));
+                    }
+                    args.title = Some(title);
+                }
+                "epic" | "feature" | "story" | "owner" | "tag" | "issue" | "tms"
+                | "description" => {
+                    let value = parse_value(input)?;
+                    let call = quote! { allure_rust::#key(#value); };
+                    args.metadata.push(Metadata { key, call });
+                }
+                "tags" => {
+                    let content;
+                    parenthesized!(content in input);
+                    let tags = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+                    for tag in tags {
+                        let call = quote! { allure_rust::tag(#tag); };
+                        args.metadata.push(Metadata {
+                            key: key.clone(),
+                            call,
+                        });
+                    }
+                }
+                "link" => {
+                    let url = parse_value(input)?;
+                    let call = quote! { allure_rust::link(#url, #url); };
+                    args.metadata.push(Metadata { key, call });
+                }
+                "severity" => {
+                    input.parse::<Token![=]>()?;
+                    let variant = parse_severity(input)?;
+                    let call = quote! { allure_rust::severity(allure_rust::Severity::#variant); };
+                    args.metadata.push(Metadata { key, call });
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!(
+                            "unknown allure_test argument `{}`, expected one of: {}",
+                            key, TEST_KEYS
+                        ),
+                    ));
+                }
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(args)
+    }
+}
+
+/// Arguments of `#[step(...)]`.
+///
+/// An optional title followed by options naming the arguments of the function,
+/// e.g. `#[step("Login as {user}", skip(password), mask(token), display(user))]`.
+#[derive(Default)]
+pub(crate) struct StepArgs {
+    pub title: Option<LitStr>,
+    /// Record no arguments at all.
+    pub skip_all: bool,
+    pub skip: Vec<Ident>,
+    pub mask: Vec<Ident>,
+    pub hide: Vec<Ident>,
+    pub exclude: Vec<Ident>,
+    pub display: Vec<Ident>,
+    pub debug: Vec<Ident>,
+}
+
+impl StepArgs {
+    /// Every argument named by an option, with the option.
+    pub fn named(&self) -> impl Iterator<Item = (&'static str, &Ident)> {
+        [
+            ("skip", &self.skip),
+            ("mask", &self.mask),
+            ("hide", &self.hide),
+            ("exclude", &self.exclude),
+            ("display", &self.display),
+            ("debug", &self.debug),
+        ]
+        .into_iter()
+        .flat_map(|(option, idents)| idents.iter().map(move |ident| (option, ident)))
+    }
+}
+
+impl Parse for StepArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = StepArgs::default();
+
+        if input.peek(LitStr) {
+            args.title = Some(input.parse()?);
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            let idents = match key.to_string().as_str() {
+                "title" => {
+                    let title = parse_value(input)?;
+                    if args.title.is_some() {
+                        return Err(syn::Error::new(key.span(), "duplicate step title"));
+                    }
+                    args.title = Some(title);
+                    None
+                }
+                "skip_all" => {
+                    args.skip_all = true;
+                    None
+                }
+                "skip" => Some(&mut args.skip),
+                "mask" => Some(&mut args.mask),
+                "hide" => Some(&mut args.hide),
+                "exclude" => Some(&mut args.exclude),
+                "display" => Some(&mut args.display),
+                "debug" => Some(&mut args.debug),
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!(
+                            "unknown step argument `{}`, expected one of: {}",
+                            key, STEP_KEYS
+                        ),
+                    ));
+                }
+            };
+            if let Some(idents) = idents {
+                let content;
+                parenthesized!(content in input);
+                // `self` is a keyword, not an identifier
+                idents.extend(Punctuated::<Ident, Token![,]>::parse_terminated_with(
+                    &content,
+                    Ident::parse_any,
+                )?);
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(args)
+    }
+}
+
+/// Arguments of `#[allure_fixture(...)]`.
+///
+/// An optional title and whether the function is a setup, the default, or a
+/// teardown, e.g. `#[allure_fixture("Stop the database", teardown)]`.
+#[derive(Default)]
+pub(crate) struct FixtureArgs {
+    pub title: Option<LitStr>,
+    pub teardown: bool,
+}
+
+impl Parse for FixtureArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = FixtureArgs::default();
+
+        if input.peek(LitStr) {
+            args.title = Some(input.parse()?);
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            match key.to_string().as_str() {
+                "title" => {
+                    let title = parse_value(input)?;
+                    if args.title.is_some() {
+                        return Err(syn::Error::new(key.span(), "duplicate fixture title"));
+                    }
+                    args.title = Some(title);
+                }
+                "setup" => args.teardown = false,
+                "teardown" => args.teardown = true,
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!(
+                            "unknown fixture argument `{}`, expected one of: {}",
+                            key, FIXTURE_KEYS
+                        ),
+                    ));
+                }
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(args)
+    }
+}
+
+fn parse_value(input: ParseStream) -> syn::Result<LitStr> {
+    input.parse::<Token![=]>()?;
+    input.parse()
+}
+
+// Accepts both `severity = critical` and `severity = "critical"`
+fn parse_severity(input: ParseStream) -> syn::Result<Ident> {
+    let (value, span) = if input.peek(LitStr) {
+        let lit: LitStr = input.parse()?;
+        (lit.value(), lit.span())
+    } else {
+        let ident: Ident = input.parse()?;
+        (ident.to_string(), ident.span())
+    };
+
+    SEVERITIES
+        .iter()
+        .find(|(name, _)| *name == value)
+        .map(|(_, variant)| Ident::new(variant, span))
+        .ok_or_else(|| {
+            syn::Error::new(
+                span,
+                format!(
+                    "unknown severity `{}`, expected one of: blocker, critical, normal, minor, trivial",
+                    value
+                ),
+            )
+        })
+}
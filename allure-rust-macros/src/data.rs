@@ -0,0 +1,324 @@
+use crate::args::TestArgs;
+use crate::{Suite, find_attr, register_ignored, step, test_body};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{FnArg, ItemFn, LitStr, Pat, Type};
+use yaml_rust2::{Yaml, YamlLoader};
+
+// Attributes that apply to the function itself rather than to the tests of
+// its rows
+const FUNCTION_ATTRS: [&str; 6] = ["doc", "cfg", "allow", "warn", "deny", "expect"];
+
+/// Turns a function into one test per row of the data file at `path`, which is
+/// relative to the crate root.
+///
+/// The function keeps the body of the test but takes its arguments as the text
+/// of their columns, and a module with its name gets a test for each row that
+/// calls it with the columns named like its arguments. The columns are parsed
+/// once the test has started, so a value that doesn't parse is reported as a
+/// broken result. The arguments are recorded as parameters, and the file is
+/// attached to every result.
+pub(crate) fn expand(
+    func: &ItemFn,
+    path: &LitStr,
+    args: &TestArgs,
+    suite: &Suite,
+) -> syn::Result<TokenStream> {
+    let rows = read_rows(path)?;
+
+    let mut arguments = Vec::new();
+    for input in &func.sig.inputs {
+        match input {
+            FnArg::Typed(pat_type) => match &*pat_type.pat {
+                Pat::Ident(pat_ident) => arguments.push((&pat_ident.ident, &*pat_type.ty)),
+                pat => {
+                    return Err(syn::Error::new_spanned(
+                        pat,
+                        "arguments filled from a data file must be plain identifiers",
+                    ));
+                }
+            },
+            FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new_spanned(
+                    receiver,
+                    "a data-driven test can't take `self`",
+                ));
+            }
+        }
+    }
+
+    let (function_attrs, row_attrs): (Vec<_>, Vec<_>) = func
+        .attrs
+        .iter()
+        .partition(|attr| FUNCTION_ATTRS.iter().any(|name| attr.path().is_ident(name)));
+    let row_attrs: Vec<_> = row_attrs
+        .into_iter()
+        .chain(
+            function_attrs
+                .iter()
+                .copied()
+                .filter(|attr| !attr.path().is_ident("doc")),
+        )
+        .collect();
+    // `#[test]`, `#[tokio::test]` and the like run each row as they are
+    let test_attr = if find_attr(&func.attrs, "test").is_some() {
+        None
+    } else {
+        Some(quote! { #[test] })
+    };
+
+    let ident = &func.sig.ident;
+    let mut row_tests = Vec::new();
+    let mut ignored_rows = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        let number = index + 1;
+        let mut values = Vec::new();
+        for (argument, _) in &arguments {
+            let name = argument.to_string();
+            let Some((_, value)) = row.iter().find(|(column, _)| *column == name) else {
+                let columns: Vec<_> = row.iter().map(|(column, _)| column.as_str()).collect();
+                return Err(syn::Error::new(
+                    path.span(),
+                    format!(
+                        "row {} of {} has no column `{}` for argument `{}`, its columns are: {}",
+                        number,
+                        path.value(),
+                        name,
+                        name,
+                        columns.join(", ")
+                    ),
+                ));
+            };
+            values.push(value);
+        }
+
+        let row_ident = format_ident!("row_{}", number);
+        let asyncness = &func.sig.asyncness;
+        let output = &func.sig.output;
+        let call = match asyncness {
+            Some(_) => quote! { super::#ident(#(#values),*).await },
+            None => quote! { super::#ident(#(#values),*) },
+        };
+        let attrs = &row_attrs;
+        row_tests.push(quote! {
+            #(#attrs)*
+            #test_attr
+            #asyncness fn #row_ident() #output {
+                #call
+            }
+        });
+        ignored_rows.extend(register_ignored(
+            func,
+            args,
+            suite,
+            &format!("{}::{}", ident, row_ident),
+        ));
+    }
+
+    let func = from_columns(func, path, &arguments);
+    let body = test_body(&func, args, suite, prelude(path)?);
+    let vis = &func.vis;
+    let sig = &func.sig;
+
+    Ok(quote! {
+        #(#function_attrs)*
+        #vis #sig {
+            #body
+        }
+
+        mod #ident {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #(#row_tests)*
+        }
+
+        #(#ignored_rows)*
+    })
+}
+
+// The function taking its arguments as the text of their columns, with a body
+// that parses and records them before running the original one
+fn from_columns(func: &ItemFn, path: &LitStr, arguments: &[(&syn::Ident, &Type)]) -> ItemFn {
+    let idents = arguments.iter().map(|(argument, _)| argument);
+    let parsed = arguments
+        .iter()
+        .filter(|(_, ty)| !is_str(ty))
+        .map(|(argument, ty)| {
+            let message = format!(
+                "can't parse argument `{}` from {{:?}} in {}",
+                argument,
+                path.value().replace('{', "{{").replace('}', "}}")
+            );
+            quote! {
+                let #argument: #ty = match #argument.parse() {
+                    Ok(value) => value,
+                    Err(_) => panic!(#message, #argument),
+                };
+            }
+        });
+    let parameters = arguments.iter().map(|(argument, _)| {
+        let name = argument.to_string();
+        let value = step::argument_value(argument);
+        quote! { allure_rust::parameter(#name, #value); }
+    });
+    let block = &func.block;
+
+    let mut func = func.clone();
+    func.sig.inputs = syn::parse_quote! { #(#idents: &str),* };
+    func.block = syn::parse_quote! {{
+        #(#parsed)*
+        #(#parameters)*
+        #block
+    }};
+    func
+}
+
+// Attaches the data file, which is embedded so the tests are rebuilt when it
+// changes
+fn prelude(path: &LitStr) -> syn::Result<TokenStream> {
+    let file = path.value();
+    let file_name = file.rsplit(['/', '\\']).next().unwrap_or(&file).to_string();
+    let attachment_type = match Format::of(path)? {
+        Format::Csv => quote! { Csv },
+        Format::Json => quote! { Json },
+        Format::Yaml => quote! { Yaml },
+    };
+
+    Ok(quote! {
+        allure_rust::add_attachment_with_type(
+            #file_name,
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", #path)),
+            allure_rust::AttachmentType::#attachment_type,
+        );
+    })
+}
+
+// `&str` arguments take the column as is, any other type is parsed from it
+fn is_str(ty: &Type) -> bool {
+    matches!(ty, Type::Reference(reference) if matches!(&*reference.elem, Type::Path(elem) if elem.path.is_ident("str")))
+}
+
+enum Format {
+    Csv,
+    Json,
+    Yaml,
+}
+
+impl Format {
+    fn of(path: &LitStr) -> syn::Result<Format> {
+        let file = path.value();
+        match file.rsplit_once('.').map(|(_, extension)| extension) {
+            Some("csv") => Ok(Format::Csv),
+            Some("json") => Ok(Format::Json),
+            Some("yaml" | "yml") => Ok(Format::Yaml),
+            _ => Err(syn::Error::new(
+                path.span(),
+                "unknown data file format, expected a .csv, .json, .yaml or .yml file",
+            )),
+        }
+    }
+}
+
+// The rows of the data file as (column, value) pairs
+fn read_rows(path: &LitStr) -> syn::Result<Vec<Vec<(String, String)>>> {
+    let error = |message: String| syn::Error::new(path.span(), message);
+    let format = Format::of(path)?;
+    let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let full_path = std::path::Path::new(&root).join(path.value());
+    let text = std::fs::read_to_string(&full_path)
+        .map_err(|err| error(format!("can't read {}: {}", full_path.display(), err)))?;
+
+    let rows = match format {
+        Format::Csv => {
+            let mut reader = csv::Reader::from_reader(text.as_bytes());
+            let headers = reader
+                .headers()
+                .map_err(|err| error(format!("invalid CSV in {}: {}", path.value(), err)))?
+                .clone();
+            let mut rows = Vec::new();
+            for record in reader.records() {
+                let record = record
+                    .map_err(|err| error(format!("invalid CSV in {}: {}", path.value(), err)))?;
+                rows.push(
+                    headers
+                        .iter()
+                        .zip(record.iter())
+                        .map(|(column, value)| (column.to_string(), value.to_string()))
+                        .collect(),
+                );
+            }
+            rows
+        }
+        Format::Json | Format::Yaml => {
+            let value: serde_json::Value = match format {
+                Format::Json => serde_json::from_str(&text)
+                    .map_err(|err| error(format!("invalid JSON in {}: {}", path.value(), err)))?,
+                _ => YamlLoader::load_from_str(&text)
+                    .map_err(|err| err.to_string())
+                    .and_then(|documents| match documents.into_iter().next() {
+                        Some(document) => yaml_to_json(document),
+                        None => Ok(serde_json::Value::Null),
+                    })
+                    .map_err(|err| error(format!("invalid YAML in {}: {}", path.value(), err)))?,
+            };
+            let serde_json::Value::Array(items) = value else {
+                return Err(error(format!("{} must hold a list of rows", path.value())));
+            };
+            let mut rows = Vec::new();
+            for item in items {
+                let serde_json::Value::Object(columns) = item else {
+                    return Err(error(format!(
+                        "the rows of {} must map column names to values",
+                        path.value()
+                    )));
+                };
+                rows.push(
+                    columns
+                        .into_iter()
+                        .map(|(column, value)| {
+                            let value = match value {
+                                serde_json::Value::String(text) => text,
+                                other => other.to_string(),
+                            };
+                            (column, value)
+                        })
+                        .collect(),
+                );
+            }
+            rows
+        }
+    };
+
+    if rows.is_empty() {
+        return Err(error(format!("{} has no rows", path.value())));
+    }
+    Ok(rows)
+}
+
+// The rows are read the same way from JSON and YAML, so YAML is turned into JSON
+fn yaml_to_json(yaml: Yaml) -> Result<serde_json::Value, String> {
+    Ok(match yaml {
+        Yaml::Null => serde_json::Value::Null,
+        Yaml::Boolean(value) => value.into(),
+        Yaml::Integer(value) => value.into(),
+        // Kept as written, e.g. `1.50` stays `1.50`
+        Yaml::Real(value) | Yaml::String(value) => value.into(),
+        Yaml::Array(items) => items
+            .into_iter()
+            .map(yaml_to_json)
+            .collect::<Result<_, _>>()?,
+        Yaml::Hash(entries) => {
+            let mut object = serde_json::Map::new();
+            for (key, value) in entries {
+                let key = match yaml_to_json(key)? {
+                    serde_json::Value::String(key) => key,
+                    key => key.to_string(),
+                };
+                object.insert(key, yaml_to_json(value)?);
+            }
+            serde_json::Value::Object(object)
+        }
+        Yaml::Alias(_) | Yaml::BadValue => return Err("aliases are not supported".to_string()),
+    })
+}
@@ -0,0 +1,85 @@
+use crate::args::FixtureArgs;
+use crate::{async_block, doc_comment, mark_assertions, output_type, step, title};
+use proc_macro2::TokenStream;
+use quote::{ToTokens, quote};
+use syn::{Ident, ItemFn};
+
+/// Runs the body of a function as a setup or teardown fixture of the test it
+/// is called from.
+///
+/// The fixture is a guard that lives as long as the body, so it also spans
+/// the whole future of an `async fn`, and a panic that unwinds through it marks
+/// it as failed. A body that evaluates to `Err` finishes it as broken, like a
+/// step. The future of an `async fn` is its own flow of the test, so what runs
+/// concurrently with it is not recorded in the fixture.
+pub(crate) fn expand(args: &FixtureArgs, func: &ItemFn) -> syn::Result<TokenStream> {
+    let sig = &func.sig;
+    let title = match &args.title {
+        Some(title) => {
+            let arguments: Vec<String> = step::bindings(sig).iter().map(Ident::to_string).collect();
+            title::expand(title, &arguments, &[])?
+        }
+        None => {
+            let title = step::humanize(&sig.ident);
+            quote! { #title }
+        }
+    };
+    let start = if args.teardown {
+        quote! { allure_rust::fixture::teardown(#title) }
+    } else {
+        quote! { allure_rust::fixture::setup(#title) }
+    };
+    let description =
+        doc_comment(&func.attrs).map(|doc| quote! { allure_rust::step_description(#doc); });
+
+    // `__fixture` is moved out only for an `Err`, and is dropped as passed
+    // otherwise
+    let finish = quote! {
+        {
+            use allure_rust::error::{ErrorDetails as _, StepError as _};
+            if let Some(error) = (&&allure_rust::error::StepOutput(&value)).step_error() {
+                __fixture.finish_with_status(
+                    allure_rust::models::Status::Broken,
+                    Some((&&allure_rust::error::ErrorReport(error)).status_details()),
+                );
+            }
+        }
+    };
+
+    let attrs = &func.attrs;
+    let vis = &func.vis;
+    let block = &func.block;
+    let output = output_type(sig);
+    let value_type = output.as_ref().map(|ty| quote! { : #ty });
+
+    if sig.asyncness.is_some() {
+        let body = async_block(sig, block, quote! {});
+        return Ok(quote! {
+            #(#attrs)*
+            #vis #sig {
+                allure_rust::future::flow(async move {
+                    let __fixture = #start;
+                    #description
+                    let value #value_type = #body.await;
+                    #finish
+                    value
+                })
+                .await
+            }
+        });
+    }
+
+    // The closure keeps a `return` in the body from skipping `finish`
+    let closure_output = output.map(|ty| quote! { -> #ty });
+    let block = mark_assertions(block.to_token_stream());
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            let __fixture = #start;
+            #description
+            let value = (move || #closure_output #block)();
+            #finish
+            value
+        }
+    })
+}
@@ -1,231 +1,610 @@
 extern crate proc_macro;
 
+mod args;
+mod data;
+mod fixture;
+mod step;
+mod title;
+
+use args::{FixtureArgs, Metadata, StepArgs, TestArgs};
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{ToTokens, quote};
 use syn::{ItemFn, ItemMod, LitStr, parse_macro_input};
 
 #[proc_macro_attribute]
 pub fn allure_test(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let input_fn = parse_macro_input!(item as ItemFn);
-    let fn_name = &input_fn.sig.ident;
-    let fn_name_str = fn_name.to_string();
-    let block = &input_fn.block;
+    let mut input_fn = parse_macro_input!(item as ItemFn);
+    let args = parse_macro_input!(attr as TestArgs);
+    if let Some(data) = take_attr(&mut input_fn.attrs, "allure_data") {
+        return data_test(&input_fn, &data, &args, &Suite::default()).into();
+    }
     let vis = &input_fn.vis;
     let attrs = &input_fn.attrs;
     let sig = &input_fn.sig;
 
-    let title = if attr.is_empty() {
-        quote! { #fn_name_str }
-    } else {
-        let title_lit = parse_macro_input!(attr as LitStr);
-        let title_str = title_lit.value();
-        quote! { #title_str }
-    };
+    let body = test_body(&input_fn, &args, &Suite::default(), quote! {});
+    let ignored = register_ignored(&input_fn, &args, &Suite::default(), &sig.ident.to_string());
 
     let output = quote! {
         #(#attrs)*
         #vis #sig {
-            allure_rust::start_test_with_context(#title, None, Some(module_path!()));
+            #body
+        }
+        #ignored
+    };
 
-            let result = std::panic::catch_unwind(|| {
-                #block
-            });
+    TokenStream::from(output)
+}
+
+// Starts the test and applies its metadata; returns the title as well
+fn test_start(func: &ItemFn, args: &TestArgs, suite: &Suite) -> (String, proc_macro2::TokenStream) {
+    let fn_name_str = func.sig.ident.to_string();
+    let metadata = args::inherit(&suite.metadata, &args.metadata);
+    let description =
+        doc_comment(&func.attrs).map(|doc| quote! { allure_rust::description(#doc); });
+
+    let title = match &args.title {
+        Some(title_lit) => title_lit.value(),
+        None => fn_name_str.clone(),
+    };
+    let suites = &suite.names;
+    let suite = match suites.first() {
+        Some(suite_name) => quote! { Some(#suite_name) },
+        None => quote! { None },
+    };
+    // Nested suites are sub-suites of the outermost one
+    let sub_suite = (suites.len() > 1).then(|| {
+        let sub_suite = suites[1..].join(" / ");
+        quote! { allure_rust::sub_suite(#sub_suite); }
+    });
+
+    let start = quote! {
+        allure_rust::start_test_with_function(
+            #title,
+            #suite,
+            Some(module_path!()),
+            Some(#fn_name_str),
+        );
+        #sub_suite
+        #description
+        #(#metadata)*
+    };
+
+    (title, start)
+}
+
+// Shared by `allure_test` and `allure_suite`, which rewrites the tests it contains itself,
+// and `allure_data`, which runs `prelude` after the test starts
+fn test_body(
+    func: &ItemFn,
+    args: &TestArgs,
+    suite: &Suite,
+    prelude: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let (title, start) = test_start(func, args, suite);
+    let parameters = case_parameters(&func.sig);
+    let start = quote! {
+        #start
+        #(#parameters)*
+        #prelude
+    };
+
+    if let Some(should_panic) = find_attr(&func.attrs, "should_panic") {
+        let expected = match attr_value(should_panic, "expected") {
+            Some(expected) => quote! { Some(#expected) },
+            None => quote! { None },
+        };
+
+        let catch_unwind = catch_unwind(func);
 
-            let is_err = result.is_err();
-            allure_rust::end_test(#title, result);
+        // The original payload is re-raised so libtest can check `expected` itself
+        return in_test_scope(
+            func,
+            quote! {
+                #start
 
-            if is_err {
-                panic!("Test failed");
+                let result = #catch_unwind;
+                allure_rust::end_test_expecting_panic(#title, &result, #expected);
+
+                if let Err(payload) = result {
+                    std::panic::resume_unwind(payload);
+                }
+            },
+        );
+    }
+
+    let report_value = if returns_result(&func.sig) {
+        quote! {
+            match &value {
+                Ok(_) => allure_rust::end_test(#title, &Ok(())),
+                Err(error) => {
+                    use allure_rust::error::ErrorDetails as _;
+                    let details = (&&allure_rust::error::ErrorReport(error)).status_details();
+                    allure_rust::end_test_with_status(
+                        #title,
+                        allure_rust::models::Status::Broken,
+                        Some(details),
+                    );
+                }
             }
         }
+    } else {
+        quote! { allure_rust::end_test(#title, &Ok(())); }
     };
 
-    TokenStream::from(output)
+    let catch_unwind = catch_unwind(func);
+
+    let body = quote! {
+        #start
+
+        let result = #catch_unwind;
+
+        match result {
+            // The value is handed back so libtest still sees a returned `Err`
+            Ok(value) => {
+                #report_value
+                value
+            }
+            // Re-raising the original payload keeps the real message in the console
+            Err(payload) => {
+                let result = Err(payload);
+                allure_rust::end_test(#title, &result);
+                std::panic::resume_unwind(result.unwrap_err());
+            }
+        }
+    };
+
+    in_test_scope(func, body)
 }
 
-#[proc_macro_attribute]
-pub fn allure_suite(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let suite_name = if attr.is_empty() {
-        return TokenStream::from(quote! {
-            compile_error!("allure_suite requires a suite name");
-        });
+// The arguments `rstest` fills in from `#[case]`s and `#[values]` tell the cases
+// of a parameterized test apart, so they become parameters of the test and part
+// of its `historyId`. Fixtures are left out.
+fn case_parameters(sig: &syn::Signature) -> Vec<proc_macro2::TokenStream> {
+    let mut bindings = Vec::new();
+    for input in &sig.inputs {
+        if let syn::FnArg::Typed(pat_type) = input {
+            let is_case = pat_type
+                .attrs
+                .iter()
+                .any(|attr| attr.path().is_ident("case") || attr.path().is_ident("values"));
+            if is_case {
+                step::pattern_bindings(&pat_type.pat, &mut bindings);
+            }
+        }
+    }
+
+    bindings
+        .iter()
+        .map(|ident| {
+            let name = ident.to_string();
+            let value = step::argument_value(ident);
+            quote! { allure_rust::parameter(#name, #value); }
+        })
+        .collect()
+}
+
+// Runs the body of the test, catching a panic; `async` bodies are awaited
+fn catch_unwind(func: &ItemFn) -> proc_macro2::TokenStream {
+    if func.sig.asyncness.is_some() {
+        let body = async_block(&func.sig, &func.block, quote! {});
+        quote! { allure_rust::future::catch_unwind(#body).await }
     } else {
-        let suite_lit = parse_macro_input!(attr as LitStr);
-        suite_lit.value()
-    };
+        let block = mark_assertions(func.block.to_token_stream());
+        let closure_output = explicit_output(&func.sig).map(|ty| quote! { -> #ty });
+        // The arguments are moved in, so fixtures passed to the test are torn
+        // down before it ends, as they are in an `async` body
+        quote! { std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || #closure_output #block)) }
+    }
+}
+
+// The assertions of `std` that `mark_assertions` replaces
+const ASSERTIONS: [&str; 6] = [
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "debug_assert",
+    "debug_assert_eq",
+    "debug_assert_ne",
+];
+
+// `assert!` and friends in a body become those of `allure_rust::assertion`,
+// which report as failed even with a custom message. Spelled out paths like
+// `std::assert!` are left alone
+fn mark_assertions(tokens: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    use proc_macro2::{Group, TokenTree};
+
+    let mut output = proc_macro2::TokenStream::new();
+    let mut previous: Option<TokenTree> = None;
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        let token = match token {
+            TokenTree::Group(group) => {
+                let mut marked = Group::new(group.delimiter(), mark_assertions(group.stream()));
+                marked.set_span(group.span());
+                TokenTree::Group(marked)
+            }
+            TokenTree::Ident(ident) => {
+                let is_macro = matches!(tokens.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == '!');
+                let in_path = matches!(&previous, Some(TokenTree::Punct(punct)) if matches!(punct.as_char(), ':' | '$'));
+                if is_macro && !in_path && ASSERTIONS.contains(&ident.to_string().as_str()) {
+                    output.extend(quote! { allure_rust:: });
+                }
+                TokenTree::Ident(ident)
+            }
+            token => token,
+        };
+        output.extend([token.clone()]);
+        previous = Some(token);
+    }
+    output
+}
+
+// Spelling out the return type lets `?` in the body infer its error type
+fn explicit_output(sig: &syn::Signature) -> Option<&syn::Type> {
+    match &sig.output {
+        syn::ReturnType::Type(_, ty) if !matches!(**ty, syn::Type::ImplTrait(_)) => Some(ty),
+        _ => None,
+    }
+}
+
+// The type of the value of a body, also of one that never returns, for code
+// that looks at the value, e.g. for an `Err`
+fn output_type(sig: &syn::Signature) -> Option<proc_macro2::TokenStream> {
+    match &sig.output {
+        syn::ReturnType::Default => Some(quote! { () }),
+        syn::ReturnType::Type(..) => explicit_output(sig).map(|ty| quote! { #ty }),
+    }
+}
+
+// The body of an `async fn` as an `async` block, after `prelude`
+fn async_block(
+    sig: &syn::Signature,
+    block: &syn::Block,
+    prelude: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let value_type = explicit_output(sig).map(|ty| quote! { : #ty });
+    let block = mark_assertions(block.to_token_stream());
+    quote! {
+        async move {
+            #prelude
+            let value #value_type = #block;
+            value
+        }
+    }
+}
+
+// An `async` test may be polled by any thread of the runtime, so the test
+// context travels with its future
+fn in_test_scope(func: &ItemFn, body: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    if func.sig.asyncness.is_some() {
+        quote! {
+            allure_rust::future::with_current_test(async move { #body }).await
+        }
+    } else {
+        body
+    }
+}
+
+// An `#[ignore]`d test never runs, so it registers how to report it as
+// skipped, for `allure_rust::ignored` to do on request. `path` is the name
+// libtest gives the test after the current module
+fn register_ignored(
+    func: &ItemFn,
+    args: &TestArgs,
+    suite: &Suite,
+    path: &str,
+) -> Option<proc_macro2::TokenStream> {
+    let ignore = find_attr(&func.attrs, "ignore")?;
+    let reason = attr_value(ignore, "reason").unwrap_or_else(|| "Test is ignored".to_string());
+    let (title, start) = test_start(func, args, suite);
+
+    Some(quote! {
+        allure_rust::ignored::submit! {
+            allure_rust::ignored::IgnoredTest {
+                path: concat!(module_path!(), "::", #path),
+                report: {
+                    fn report() {
+                        #start
+                        allure_rust::end_test_with_status(
+                            #title,
+                            allure_rust::models::Status::Skipped,
+                            Some(allure_rust::models::StatusDetails {
+                                message: Some(#reason.to_string()),
+                                trace: None,
+                            }),
+                        );
+                    }
+                    report
+                },
+            }
+        }
+    })
+}
+
+fn find_attr<'a>(attrs: &'a [syn::Attribute], name: &str) -> Option<&'a syn::Attribute> {
+    attrs.iter().find(|attr| {
+        attr.path()
+            .segments
+            .last()
+            .map(|seg| seg.ident == name)
+            .unwrap_or(false)
+    })
+}
+
+// Removes the attribute from `attrs`, if it is there
+fn take_attr(attrs: &mut Vec<syn::Attribute>, name: &str) -> Option<syn::Attribute> {
+    let index = attrs.iter().position(|attr| {
+        attr.path()
+            .segments
+            .last()
+            .is_some_and(|seg| seg.ident == name)
+    })?;
+    Some(attrs.remove(index))
+}
+
+// The string of `#[attr = "..."]` or `#[attr(key = "...")]`
+fn attr_value(attr: &syn::Attribute, key: &str) -> Option<String> {
+    match &attr.meta {
+        syn::Meta::NameValue(syn::MetaNameValue {
+            value:
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(lit),
+                    ..
+                }),
+            ..
+        }) => Some(lit.value()),
+        syn::Meta::List(list) => {
+            let mut value = None;
+            let _ = list.parse_nested_meta(|meta| {
+                if meta.path.is_ident(key) {
+                    value = Some(meta.value()?.parse::<LitStr>()?.value());
+                }
+                Ok(())
+            });
+            value
+        }
+        _ => None,
+    }
+}
+
+// `Result`, `io::Result`, `anyhow::Result` and other aliases named `Result`
+fn returns_result(sig: &syn::Signature) -> bool {
+    match &sig.output {
+        syn::ReturnType::Type(_, ty) => match &**ty {
+            syn::Type::Path(type_path) => type_path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Result"),
+            _ => false,
+        },
+        syn::ReturnType::Default => false,
+    }
+}
 
+// Joins `///` doc comment lines, which are passed to macros as `#[doc = "..."]`
+fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(lit),
+                        ..
+                    }),
+                ..
+            }) => Some(lit.value()),
+            _ => None,
+        })
+        .map(|line| {
+            line.strip_prefix(' ')
+                .unwrap_or(&line)
+                .trim_end()
+                .to_string()
+        })
+        .collect();
+
+    let doc = lines.join("\n").trim().to_string();
+    if doc.is_empty() { None } else { Some(doc) }
+}
+
+#[proc_macro_attribute]
+pub fn allure_suite(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as TestArgs);
     let input_mod = parse_macro_input!(item as ItemMod);
+
+    Suite::default()
+        .nested(args)
+        .map(|suite| suite_module(&input_mod, &suite))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The suites a test is nested in, outermost first, and the metadata they pass
+/// on to it.
+#[derive(Default)]
+struct Suite {
+    names: Vec<String>,
+    metadata: Vec<Metadata>,
+}
+
+impl Suite {
+    // The suite of `#[allure_suite(args)]` inside this one
+    fn nested(&self, args: TestArgs) -> syn::Result<Suite> {
+        let name = args.title.ok_or_else(|| {
+            syn::Error::new(
+                proc_macro2::Span::call_site(),
+                "allure_suite requires a suite name",
+            )
+        })?;
+        if let Some(description) = args.metadata.iter().find(|m| m.key == "description") {
+            return Err(syn::Error::new(
+                description.key.span(),
+                "a suite has no description, document its tests instead",
+            ));
+        }
+
+        Ok(Suite {
+            names: self.names.iter().cloned().chain([name.value()]).collect(),
+            metadata: args::inherit(&self.metadata, &args.metadata),
+        })
+    }
+}
+
+// Rewrites the tests of a suite module and of the modules inside it
+fn suite_module(input_mod: &ItemMod, suite: &Suite) -> proc_macro2::TokenStream {
     let mod_name = &input_mod.ident;
     let vis = &input_mod.vis;
     let attrs = &input_mod.attrs;
     let content = &input_mod.content;
 
     if let Some((_, items)) = content {
-        let modified_items: Vec<_> = items
-            .iter()
-            .map(|item| {
-                if let syn::Item::Fn(func) = item {
-                    let has_allure_test = func.attrs.iter().any(|attr| {
-                        attr.path()
-                            .segments
-                            .last()
-                            .map(|seg| seg.ident == "allure_test")
-                            .unwrap_or(false)
-                    });
-
-                    if has_allure_test {
-                        let fn_name = &func.sig.ident;
-                        let fn_vis = &func.vis;
-                        let fn_attrs = &func.attrs;
-                        let fn_sig = &func.sig;
-                        let fn_block = &func.block;
-
-                        let allure_test_attr = fn_attrs.iter().find(|attr| {
-                            attr.path()
-                                .segments
-                                .last()
-                                .map(|seg| seg.ident == "allure_test")
-                                .unwrap_or(false)
-                        });
-
-                        let test_title = if let Some(attr) = allure_test_attr {
-                            if let Ok(lit) = attr.parse_args::<LitStr>() {
-                                lit.value()
-                            } else {
-                                fn_name.to_string()
-                            }
-                        } else {
-                            fn_name.to_string()
-                        };
-
-                        let other_attrs: Vec<_> = fn_attrs
-                            .iter()
-                            .filter(|attr| {
-                                let path = attr.path();
-                                let last_seg = path.segments.last().map(|seg| &seg.ident);
-                                !matches!(
-                                    last_seg.map(|i| i.to_string()).as_deref(),
-                                    Some("allure_test") | Some("test")
-                                )
-                            })
-                            .collect();
-
-                        return quote! {
-                            #(#other_attrs)*
-                            #[test]
-                            #fn_vis #fn_sig {
-                                allure_rust::start_test_with_suite(#test_title, Some(#suite_name));
-
-                                let result = std::panic::catch_unwind(|| {
-                                    #fn_block
-                                });
-
-                                let is_err = result.is_err();
-                                allure_rust::end_test(#test_title, result);
-
-                                if is_err {
-                                    panic!("Test failed");
-                                }
-                            }
-                        };
-                    }
-                }
-                quote! { #item }
-            })
-            .collect();
+        let modified_items: Vec<_> = items.iter().map(|item| suite_item(item, suite)).collect();
 
-        let output = quote! {
+        quote! {
             #(#attrs)*
             #vis mod #mod_name {
                 #(#modified_items)*
             }
-        };
-
-        TokenStream::from(output)
+        }
     } else {
-        TokenStream::from(quote! {
+        quote! {
             compile_error!("allure_suite can only be applied to modules with content");
-        })
+        }
     }
 }
 
-#[proc_macro_attribute]
-pub fn step(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let input_fn = parse_macro_input!(item as ItemFn);
-    let fn_name = &input_fn.sig.ident;
-    let fn_name_str = fn_name.to_string();
-    let block = &input_fn.block;
-    let vis = &input_fn.vis;
-    let attrs = &input_fn.attrs;
-    let sig = &input_fn.sig;
+fn suite_item(item: &syn::Item, suite: &Suite) -> proc_macro2::TokenStream {
+    match item {
+        // A nested suite is handled here, so it knows the suites around it
+        syn::Item::Mod(inner) if find_attr(&inner.attrs, "allure_suite").is_some() => {
+            let mut inner = inner.clone();
+            let attr = take_attr(&mut inner.attrs, "allure_suite");
+            let nested = match attr.map(|attr| attr.meta) {
+                Some(syn::Meta::List(list)) => list
+                    .parse_args::<TestArgs>()
+                    .and_then(|args| suite.nested(args)),
+                _ => suite.nested(TestArgs::default()),
+            };
+            match nested {
+                Ok(nested) => suite_module(&inner, &nested),
+                Err(err) => err.to_compile_error(),
+            }
+        }
+        // Other inline modules are part of the suite
+        syn::Item::Mod(inner) if inner.content.is_some() => suite_module(inner, suite),
+        syn::Item::Fn(func)
+            if find_attr(&func.attrs, "allure_test").is_some()
+                || find_attr(&func.attrs, "allure_data").is_some() =>
+        {
+            let mut func = func.clone();
+            let test_args = match take_attr(&mut func.attrs, "allure_test").map(|attr| attr.meta) {
+                Some(syn::Meta::List(list)) => match list.parse_args::<TestArgs>() {
+                    Ok(args) => args,
+                    Err(err) => return err.to_compile_error(),
+                },
+                _ => TestArgs::default(),
+            };
+            if let Some(data) = take_attr(&mut func.attrs, "allure_data") {
+                return data_test(&func, &data, &test_args, suite);
+            }
 
-    let title = if attr.is_empty() {
-        quote! { #fn_name_str }
-    } else {
-        let title_lit = parse_macro_input!(attr as LitStr);
-        let title_str = title_lit.value();
-        quote! { #title_str }
-    };
+            // `#[test]`, `#[tokio::test]` and the like run the test as they are
+            let test_attr = if find_attr(&func.attrs, "test").is_some() {
+                None
+            } else {
+                Some(quote! { #[test] })
+            };
+            let fn_vis = &func.vis;
+            let fn_attrs = &func.attrs;
+            let fn_sig = &func.sig;
 
-    let params: Vec<_> = sig
-        .inputs
-        .iter()
-        .filter_map(|arg| {
-            if let syn::FnArg::Typed(pat_type) = arg {
-                if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
-                    let param_name = &pat_ident.ident;
-                    let param_name_str = param_name.to_string();
-                    return Some(quote! {
-                        allure_rust::models::Parameter {
-                            name: #param_name_str.to_string(),
-                            value: format!("{:?}", #param_name),
-                        }
-                    });
+            let body = test_body(&func, &test_args, suite, quote! {});
+            let ignored = register_ignored(&func, &test_args, suite, &fn_sig.ident.to_string());
+
+            quote! {
+                #(#fn_attrs)*
+                #test_attr
+                #fn_vis #fn_sig {
+                    #body
                 }
+                #ignored
             }
-            None
-        })
-        .collect();
+        }
+        _ => quote! { #item },
+    }
+}
 
-    let params_vec = if params.is_empty() {
-        quote! { Vec::new() }
-    } else {
-        quote! { vec![#(#params),*] }
+#[proc_macro_attribute]
+pub fn allure_data(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(attr as LitStr);
+    let mut input_fn = parse_macro_input!(item as ItemFn);
+
+    // The arguments of an `#[allure_test]` below are the test's
+    let args = match take_attr(&mut input_fn.attrs, "allure_test").map(|attr| attr.meta) {
+        Some(syn::Meta::List(list)) => match list.parse_args::<TestArgs>() {
+            Ok(args) => args,
+            Err(err) => return err.to_compile_error().into(),
+        },
+        _ => TestArgs::default(),
     };
 
-    let output = quote! {
-        #(#attrs)*
-        #vis #sig {
-            let __params = #params_vec;
-            allure_rust::start_step_with_params(#title, __params);
-
-            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                #block
-            }));
-
-            let is_err = result.is_err();
-            let step_result: std::thread::Result<()> = match &result {
-                Ok(_) => Ok(()),
-                Err(e) => {
-                    let cloned: Box<dyn std::any::Any + Send> = if let Some(s) = e.downcast_ref::<&'static str>() {
-                        Box::new(*s)
-                    } else if let Some(s) = e.downcast_ref::<String>() {
-                        Box::new(s.clone())
-                    } else {
-                        Box::new("Unknown error")
-                    };
-                    Err(cloned)
-                }
-            };
-            allure_rust::end_step(&step_result);
+    data::expand(&input_fn, &path, &args, &Suite::default())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-            if is_err {
-                std::panic::resume_unwind(result.unwrap_err());
-            } else {
-                result.unwrap()
-            }
-        }
-    };
+// A test with an `#[allure_data("...")]` attribute
+fn data_test(
+    func: &ItemFn,
+    data: &syn::Attribute,
+    args: &TestArgs,
+    suite: &Suite,
+) -> proc_macro2::TokenStream {
+    data.parse_args::<LitStr>()
+        .and_then(|path| data::expand(func, &path, args, suite))
+        .unwrap_or_else(syn::Error::into_compile_error)
+}
 
-    TokenStream::from(output)
+#[proc_macro_attribute]
+pub fn step(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input_fn = parse_macro_input!(item as ItemFn);
+    let args = parse_macro_input!(attr as StepArgs);
+
+    step::expand(
+        &args,
+        &input_fn.attrs,
+        &input_fn.vis,
+        &input_fn.sig,
+        &input_fn.block,
+    )
+    .unwrap_or_else(syn::Error::into_compile_error)
+    .into()
+}
+
+#[proc_macro_attribute]
+pub fn allure_steps(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return TokenStream::from(quote! {
+            compile_error!("allure_steps takes no arguments, use #[step(...)] on the methods");
+        });
+    }
+    let item = parse_macro_input!(item as syn::Item);
+
+    step::expand_methods(item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_attribute]
+pub fn allure_fixture(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input_fn = parse_macro_input!(item as ItemFn);
+    let args = parse_macro_input!(attr as FixtureArgs);
+
+    fixture::expand(&args, &input_fn)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
@@ -0,0 +1,322 @@
+use crate::args::StepArgs;
+use crate::{async_block, doc_comment, mark_assertions, output_type, take_attr, title};
+use proc_macro2::TokenStream;
+use quote::{ToTokens, quote};
+use syn::{Attribute, Block, FnArg, Ident, Pat, Signature, Visibility};
+
+/// Wraps the body of a function in a step, recording its arguments as
+/// parameters according to `args`.
+pub(crate) fn expand(
+    args: &StepArgs,
+    attrs: &[Attribute],
+    vis: &Visibility,
+    sig: &Signature,
+    block: &Block,
+) -> syn::Result<TokenStream> {
+    let bindings = bindings(sig);
+    for (option, ident) in args.named() {
+        if !bindings.contains(ident) {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!(
+                    "`{}({})` does not name an argument of the function",
+                    option, ident
+                ),
+            ));
+        }
+        if option == "debug" && args.display.contains(ident) {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!(
+                    "`{}` can't be recorded with both `display` and `debug`",
+                    ident
+                ),
+            ));
+        }
+        if option != "skip" && args.skip.contains(ident) {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!(
+                    "`{}` is skipped, it can't be recorded with `{}`",
+                    ident, option
+                ),
+            ));
+        }
+    }
+
+    let title = match &args.title {
+        Some(title) => {
+            let arguments: Vec<String> = bindings.iter().map(Ident::to_string).collect();
+            let secret: Vec<String> = bindings
+                .iter()
+                .filter(|ident| {
+                    args.skip_all
+                        || [&args.skip, &args.mask, &args.hide]
+                            .iter()
+                            .any(|idents| idents.contains(ident))
+                })
+                .map(Ident::to_string)
+                .collect();
+            title::expand(title, &arguments, &secret)?
+        }
+        None => {
+            let fn_name_str = sig.ident.to_string();
+            quote! { #fn_name_str }
+        }
+    };
+
+    let params: Vec<_> = bindings
+        .iter()
+        .filter(|ident| is_recorded(args, ident))
+        .map(|ident| parameter(args, ident))
+        .collect();
+    let params_vec = if params.is_empty() {
+        quote! { Vec::new() }
+    } else {
+        quote! { vec![#(#params),*] }
+    };
+
+    let description = doc_comment(attrs).map(|doc| quote! { allure_rust::step_description(#doc); });
+
+    // The step of an `async fn` spans the whole future, not just its creation
+    if sig.asyncness.is_some() {
+        let body = async_block(sig, block, quote! { #description });
+        return Ok(quote! {
+            #(#attrs)*
+            #vis #sig {
+                let __params = #params_vec;
+                allure_rust::future::step(#title, __params, #body).await
+            }
+        });
+    }
+
+    let block = mark_assertions(block.to_token_stream());
+    let closure_output = output_type(sig).map(|ty| quote! { -> #ty });
+    // A step that returns `Err` is broken, like an `allure_step!` block
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            let __params = #params_vec;
+            allure_rust::start_step_with_params(#title, __params);
+            #description
+
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| #closure_output #block));
+
+            match &result {
+                Ok(value) => {
+                    use allure_rust::error::{ErrorDetails as _, StepError as _};
+                    match (&&allure_rust::error::StepOutput(value)).step_error() {
+                        Some(error) => allure_rust::end_step_with_status(
+                            allure_rust::models::Status::Broken,
+                            Some((&&allure_rust::error::ErrorReport(error)).status_details()),
+                        ),
+                        None => allure_rust::end_step(&result),
+                    }
+                }
+                Err(_) => allure_rust::end_step(&result),
+            }
+
+            match result {
+                Ok(value) => value,
+                Err(payload) => std::panic::resume_unwind(payload),
+            }
+        }
+    })
+}
+
+// `self` is only recorded when an option asks for it
+fn is_recorded(args: &StepArgs, ident: &Ident) -> bool {
+    if args.skip_all || args.skip.contains(ident) {
+        return false;
+    }
+    ident != "self" || args.named().any(|(_, named)| named == ident)
+}
+
+fn parameter(args: &StepArgs, ident: &Ident) -> TokenStream {
+    let name = ident.to_string();
+    // A masked value never reaches the results
+    let value = if args.mask.contains(ident) {
+        quote! { "******".to_string() }
+    } else if args.display.contains(ident) {
+        quote! { format!("{}", #ident) }
+    } else if args.debug.contains(ident) {
+        quote! { format!("{:?}", #ident) }
+    } else {
+        argument_value(ident)
+    };
+    let mode = if args.mask.contains(ident) {
+        quote! { Masked }
+    } else if args.hide.contains(ident) {
+        quote! { Hidden }
+    } else {
+        quote! { Default }
+    };
+    let excluded = args.exclude.contains(ident);
+
+    quote! {
+        allure_rust::models::Parameter {
+            name: #name.to_string(),
+            value: #value,
+            mode: allure_rust::models::ParameterMode::#mode,
+            excluded: #excluded,
+        }
+    }
+}
+
+/// The `Debug` of an argument, or the name of its type.
+pub(crate) fn argument_value(ident: &Ident) -> TokenStream {
+    quote! {{
+        use allure_rust::parameter::FormatArgument as _;
+        (&&allure_rust::parameter::ArgumentValue(&#ident)).argument_value()
+    }}
+}
+
+// The names the arguments bind, in order: `self` and the identifiers in
+// patterns such as `(from, to): (Account, Account)`
+pub(crate) fn bindings(sig: &Signature) -> Vec<Ident> {
+    let mut bindings = Vec::new();
+    for input in &sig.inputs {
+        match input {
+            FnArg::Receiver(receiver) => bindings.push(Ident::from(receiver.self_token)),
+            FnArg::Typed(pat_type) => pattern_bindings(&pat_type.pat, &mut bindings),
+        }
+    }
+    bindings
+}
+
+pub(crate) fn pattern_bindings(pat: &Pat, bindings: &mut Vec<Ident>) {
+    match pat {
+        Pat::Ident(pat_ident) => {
+            bindings.push(pat_ident.ident.clone());
+            if let Some((_, subpat)) = &pat_ident.subpat {
+                pattern_bindings(subpat, bindings);
+            }
+        }
+        Pat::Tuple(tuple) => tuple
+            .elems
+            .iter()
+            .for_each(|elem| pattern_bindings(elem, bindings)),
+        Pat::TupleStruct(tuple) => tuple
+            .elems
+            .iter()
+            .for_each(|elem| pattern_bindings(elem, bindings)),
+        Pat::Slice(slice) => slice
+            .elems
+            .iter()
+            .for_each(|elem| pattern_bindings(elem, bindings)),
+        Pat::Struct(pat_struct) => pat_struct
+            .fields
+            .iter()
+            .for_each(|field| pattern_bindings(&field.pat, bindings)),
+        Pat::Reference(reference) => pattern_bindings(&reference.pat, bindings),
+        Pat::Paren(paren) => pattern_bindings(&paren.pat, bindings),
+        Pat::Type(pat_type) => pattern_bindings(&pat_type.pat, bindings),
+        _ => {}
+    }
+}
+
+/// Turns the methods of an `impl` block, or the default methods of a trait,
+/// into steps titled after their names. Methods opt out with `#[no_step]`, and
+/// `#[step(...)]` on a method overrides the title or records its arguments
+/// differently.
+pub(crate) fn expand_methods(item: syn::Item) -> syn::Result<TokenStream> {
+    match item {
+        syn::Item::Impl(mut item_impl) => {
+            // Methods of a trait implementation are as public as the trait
+            let trait_impl = item_impl.trait_.is_some();
+            for impl_item in &mut item_impl.items {
+                if let syn::ImplItem::Fn(method) = impl_item {
+                    let public = trait_impl || !matches!(method.vis, Visibility::Inherited);
+                    if let Some(args) = method_args(&mut method.attrs, &method.sig, public)? {
+                        let expanded = expand(
+                            &args,
+                            &method.attrs,
+                            &method.vis,
+                            &method.sig,
+                            &method.block,
+                        )?;
+                        *impl_item = syn::ImplItem::Verbatim(expanded);
+                    }
+                }
+            }
+            Ok(quote! { #item_impl })
+        }
+        syn::Item::Trait(mut item_trait) => {
+            for trait_item in &mut item_trait.items {
+                if let syn::TraitItem::Fn(method) = trait_item {
+                    let Some(block) = method.default.take() else {
+                        continue;
+                    };
+                    match method_args(&mut method.attrs, &method.sig, true)? {
+                        Some(args) => {
+                            let expanded = expand(
+                                &args,
+                                &method.attrs,
+                                &Visibility::Inherited,
+                                &method.sig,
+                                &block,
+                            )?;
+                            *trait_item = syn::TraitItem::Verbatim(expanded);
+                        }
+                        None => method.default = Some(block),
+                    }
+                }
+            }
+            Ok(quote! { #item_trait })
+        }
+        item => Err(syn::Error::new_spanned(
+            item,
+            "allure_steps can only be applied to impl blocks and traits",
+        )),
+    }
+}
+
+// The step arguments of a method, or `None` if it is not a step, removing
+// the attributes that say so
+fn method_args(
+    attrs: &mut Vec<Attribute>,
+    sig: &Signature,
+    public: bool,
+) -> syn::Result<Option<StepArgs>> {
+    let opted_out = take_attr(attrs, "no_step").is_some();
+    let explicit = take_attr(attrs, "step");
+    if opted_out {
+        return match explicit {
+            Some(attr) => Err(syn::Error::new_spanned(
+                attr,
+                "a method can't be both `#[step]` and `#[no_step]`",
+            )),
+            None => Ok(None),
+        };
+    }
+
+    let mut args = match explicit {
+        Some(attr) => match &attr.meta {
+            syn::Meta::List(list) => list.parse_args::<StepArgs>()?,
+            _ => StepArgs::default(),
+        },
+        // Associated functions such as constructors are not steps by default
+        None if public && sig.receiver().is_some() => StepArgs::default(),
+        None => return Ok(None),
+    };
+    if args.title.is_none() {
+        args.title = Some(syn::LitStr::new(&humanize(&sig.ident), sig.ident.span()));
+    }
+    Ok(Some(args))
+}
+
+// `add_to_cart` becomes "Add to cart"
+pub(crate) fn humanize(ident: &Ident) -> String {
+    let name = ident.to_string();
+    let words = name
+        .trim_start_matches("r#")
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name,
+    }
+}
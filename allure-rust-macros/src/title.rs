@@ -0,0 +1,126 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::LitStr;
+
+/// Builds the title of a step from a template such as
+/// `"Transfer {amount} from {from.id} to {to.id}"`.
+///
+/// Placeholders name an argument of the function (or `self`), optionally followed
+/// by field accesses and a format spec (`{amount:.2}`, `{user:?}`), and are
+/// formatted with `Display` unless the spec says otherwise. `{{` and `}}` are
+/// literal braces. A template without placeholders stays a string literal.
+///
+/// The value of a `secret` argument, one that is skipped, masked or hidden, never
+/// ends up in the title: its placeholders show `******` instead.
+pub(crate) fn expand(
+    title: &LitStr,
+    arguments: &[String],
+    secret: &[String],
+) -> syn::Result<TokenStream> {
+    let template = title.value();
+    if !template.contains(['{', '}']) {
+        return Ok(quote! { #title });
+    }
+
+    let mut format = String::new();
+    let mut values = Vec::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                format.push_str("{{");
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                format.push_str("}}");
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => {
+                            return Err(syn::Error::new(
+                                title.span(),
+                                "unclosed `{` in step title, use `{{` for a literal brace",
+                            ));
+                        }
+                    }
+                }
+                let (path, spec) = match placeholder.split_once(':') {
+                    Some((path, spec)) => (path.trim(), Some(spec)),
+                    None => (placeholder.trim(), None),
+                };
+                let value = placeholder_value(title, path, arguments)?;
+                let root = path.split('.').next().unwrap_or_default();
+                if secret.iter().any(|argument| argument == root) {
+                    format.push_str("******");
+                    continue;
+                }
+                values.push(value);
+                format.push('{');
+                if let Some(spec) = spec {
+                    format.push(':');
+                    format.push_str(spec);
+                }
+                format.push('}');
+            }
+            '}' => {
+                return Err(syn::Error::new(
+                    title.span(),
+                    "unmatched `}` in step title, use `}}` for a literal brace",
+                ));
+            }
+            c => format.push(c),
+        }
+    }
+
+    Ok(quote! { format!(#format, #(#values),*) })
+}
+
+// `amount`, `from.id`, `self.account.0`: an argument followed by field accesses
+fn placeholder_value(title: &LitStr, path: &str, arguments: &[String]) -> syn::Result<TokenStream> {
+    let mut segments = path.split('.');
+    let root = segments.next().unwrap_or_default();
+    if !arguments.iter().any(|argument| argument == root) {
+        let expected = if arguments.is_empty() {
+            "the function has no arguments".to_string()
+        } else {
+            format!("expected one of the arguments: {}", arguments.join(", "))
+        };
+        let message = if root.is_empty() {
+            format!(
+                "step title placeholders must name an argument, {}",
+                expected
+            )
+        } else {
+            format!(
+                "unknown placeholder `{{{}}}` in step title, {}",
+                path, expected
+            )
+        };
+        return Err(syn::Error::new(title.span(), message));
+    }
+
+    let is_field = |segment: &str| {
+        segment
+            .chars()
+            .all(|c| c == '_' || c.is_ascii_alphanumeric())
+            && !segment.is_empty()
+    };
+    if !segments.all(is_field) {
+        return Err(syn::Error::new(
+            title.span(),
+            format!(
+                "invalid placeholder `{{{}}}` in step title, expected an argument followed by field accesses",
+                path
+            ),
+        ));
+    }
+
+    let value: syn::Expr =
+        syn::parse_str(path).map_err(|err| syn::Error::new(title.span(), err))?;
+    Ok(quote! { #value })
+}
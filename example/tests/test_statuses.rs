@@ -69,3 +69,11 @@ fn test_conditional() {
 
     assert!(condition, "Condition should be true");
 }
+
+#[allure_test("Test returning an error (broken)")]
+#[test]
+fn test_returned_error() -> Result<(), Box<dyn std::error::Error>> {
+    let port: u16 = "http".parse()?;
+    assert_eq!(port, 80);
+    Ok(())
+}
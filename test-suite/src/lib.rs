@@ -38,6 +38,8 @@ mod tests {
         let param = Parameter {
             name: "username".to_string(),
             value: "john_doe".to_string(),
+            mode: ParameterMode::Default,
+            excluded: false,
         };
 
         assert_eq!(param.name, "username");
@@ -83,6 +85,8 @@ mod tests {
     fn test_test_step_creation() {
         let step = TestStep {
             name: "Login step".to_string(),
+            description: Some("Logs in as admin".to_string()),
+            description_html: None,
             status: Status::Passed,
             status_details: None,
             stage: "finished".to_string(),
@@ -104,6 +108,8 @@ mod tests {
     fn test_nested_steps() {
         let inner_step = TestStep {
             name: "Inner step".to_string(),
+            description: None,
+            description_html: None,
             status: Status::Passed,
             status_details: None,
             stage: "finished".to_string(),
@@ -116,6 +122,8 @@ mod tests {
 
         let outer_step = TestStep {
             name: "Outer step".to_string(),
+            description: None,
+            description_html: None,
             status: Status::Passed,
             status_details: None,
             stage: "finished".to_string(),
@@ -135,13 +143,16 @@ mod tests {
         use uuid::Uuid;
 
         let uuid = Uuid::new_v4();
-        let history_id = Uuid::new_v4();
+        let history_id = Uuid::new_v4().to_string();
 
         let test_result = TestResult {
             uuid,
             history_id,
+            test_case_id: "abc123".to_string(),
             name: "Test name".to_string(),
+            full_name: "tests::test_name".to_string(),
             description: Some("Test description".to_string()),
+            description_html: None,
             status: Status::Passed,
             status_details: None,
             stage: "finished".to_string(),
@@ -158,6 +169,8 @@ mod tests {
         assert!(json.contains("\"name\":\"Test name\""));
         assert!(json.contains("\"status\":\"passed\""));
         assert!(json.contains("\"stage\":\"finished\""));
+        assert!(json.contains("\"testCaseId\":\"abc123\""));
+        assert!(json.contains("\"fullName\":\"tests::test_name\""));
     }
 
     #[test]
@@ -166,9 +179,12 @@ mod tests {
 
         let test_result = TestResult {
             uuid: Uuid::new_v4(),
-            history_id: Uuid::new_v4(),
+            history_id: Uuid::new_v4().to_string(),
+            test_case_id: Uuid::new_v4().to_string(),
             name: "Test with labels".to_string(),
+            full_name: "tests::example".to_string(),
             description: None,
+            description_html: None,
             status: Status::Passed,
             status_details: None,
             stage: "finished".to_string(),
@@ -201,9 +217,12 @@ mod tests {
 
         let test_result = TestResult {
             uuid: Uuid::new_v4(),
-            history_id: Uuid::new_v4(),
+            history_id: Uuid::new_v4().to_string(),
+            test_case_id: Uuid::new_v4().to_string(),
             name: "Parameterized test".to_string(),
+            full_name: "tests::example".to_string(),
             description: None,
+            description_html: None,
             status: Status::Passed,
             status_details: None,
             stage: "finished".to_string(),
@@ -214,10 +233,14 @@ mod tests {
                 Parameter {
                     name: "input".to_string(),
                     value: "42".to_string(),
+                    mode: ParameterMode::Default,
+                    excluded: false,
                 },
                 Parameter {
                     name: "expected".to_string(),
                     value: "42".to_string(),
+                    mode: ParameterMode::Default,
+                    excluded: false,
                 },
             ],
             links: vec![],
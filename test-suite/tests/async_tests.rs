@@ -0,0 +1,270 @@
+mod common;
+
+use allure_rust::{allure_step, allure_steps, allure_suite, allure_test, parameter, step};
+use common::{has_label, with_results_dir};
+use serial_test::serial;
+use std::time::Duration;
+
+fn multi_thread_runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(4)
+        .enable_time()
+        .build()
+        .unwrap()
+}
+
+#[test]
+#[serial]
+fn test_async_test_keeps_context_across_worker_threads() {
+    #[allure_test("Async refund", epic = "Billing")]
+    async fn async_refund() {
+        for attempt in 0..5 {
+            tokio::task::yield_now().await;
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            allure_step!("Poll refund", {
+                parameter(format!("attempt {}", attempt), attempt);
+            });
+        }
+    }
+
+    // Spawned rather than blocked on, so the test runs on the worker threads
+    let results = with_results_dir(|| {
+        multi_thread_runtime()
+            .block_on(async { tokio::spawn(async_refund()).await })
+            .unwrap();
+    });
+
+    assert_eq!(results.len(), 1);
+    let result = &results[0];
+    assert_eq!(result["name"], "Async refund");
+    assert_eq!(result["status"], "passed");
+    assert!(has_label(result, "epic", "Billing"));
+    let steps = result["steps"].as_array().unwrap();
+    assert_eq!(steps.len(), 5);
+    for (attempt, step) in steps.iter().enumerate() {
+        assert_eq!(step["parameters"][0]["value"], attempt.to_string());
+    }
+}
+
+#[test]
+#[serial]
+fn test_async_test_failures_are_reported() {
+    #[allure_test]
+    async fn async_failing() {
+        tokio::task::yield_now().await;
+        assert_eq!(1 + 1, 3);
+    }
+
+    #[allure_test]
+    async fn async_returning_err() -> Result<(), String> {
+        tokio::task::yield_now().await;
+        Err("gateway timeout".to_string())?;
+        Ok(())
+    }
+
+    let runtime = multi_thread_runtime();
+    let results = with_results_dir(|| {
+        let joined = runtime.block_on(async { tokio::spawn(async_failing()).await });
+        assert!(joined.unwrap_err().is_panic());
+    });
+    assert_eq!(results[0]["status"], "failed");
+    assert!(
+        results[0]["statusDetails"]["trace"]
+            .as_str()
+            .unwrap()
+            .contains("async_tests.rs")
+    );
+
+    let results = with_results_dir(|| {
+        let value = runtime.block_on(async { tokio::spawn(async_returning_err()).await });
+        assert!(value.unwrap().is_err());
+    });
+    assert_eq!(results[0]["status"], "broken");
+    assert_eq!(
+        results[0]["statusDetails"]["message"],
+        "\"gateway timeout\""
+    );
+}
+
+#[test]
+#[serial]
+fn test_async_std_test() {
+    #[allure_test]
+    async fn async_std_refund() {
+        async_std::task::yield_now().await;
+        allure_step!("Refund", {});
+    }
+
+    let results = with_results_dir(|| {
+        async_std::task::block_on(async_std::task::spawn(async_std_refund()));
+    });
+
+    assert_eq!(results[0]["status"], "passed");
+    assert_eq!(results[0]["steps"][0]["name"], "Refund");
+}
+
+#[step("Load order {id}")]
+async fn load_order(id: u32) -> u32 {
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    allure_step!("Parse order", {});
+    id
+}
+
+#[step("Reserve stock")]
+async fn reserve_stock(count: u32) {
+    for _ in 0..count {
+        tokio::task::yield_now().await;
+        allure_step!("Reserve item", {});
+    }
+}
+
+#[step("Charge card")]
+async fn charge_card() {
+    tokio::task::yield_now().await;
+    let approved = false;
+    assert!(approved, "card declined");
+}
+
+#[test]
+#[serial]
+fn test_async_step_spans_the_await() {
+    #[allure_test]
+    async fn checkout() {
+        assert_eq!(load_order(7).await, 7);
+    }
+
+    let results = with_results_dir(|| {
+        multi_thread_runtime()
+            .block_on(async { tokio::spawn(checkout()).await })
+            .unwrap();
+    });
+
+    let step = &results[0]["steps"][0];
+    assert_eq!(step["name"], "Load order 7");
+    assert_eq!(step["status"], "passed");
+    assert_eq!(step["parameters"][0]["value"], "7");
+    assert!(step["stop"].as_i64().unwrap() - step["start"].as_i64().unwrap() >= 20);
+    assert_eq!(step["steps"][0]["name"], "Parse order");
+}
+
+#[test]
+#[serial]
+fn test_concurrent_async_steps_nest_independently() {
+    #[allure_test]
+    async fn concurrent_checkout() {
+        allure_step!("Prepare order {}", 1, async {
+            tokio::join!(load_order(1), reserve_stock(3));
+        })
+        .await;
+    }
+
+    let results = with_results_dir(|| {
+        multi_thread_runtime()
+            .block_on(async { tokio::spawn(concurrent_checkout()).await })
+            .unwrap();
+    });
+
+    let steps = results[0]["steps"].as_array().unwrap();
+    assert_eq!(steps.len(), 1);
+    let prepare = &steps[0];
+    assert_eq!(prepare["name"], "Prepare order 1");
+    assert_eq!(prepare["stage"], "finished");
+
+    let children = prepare["steps"].as_array().unwrap();
+    assert_eq!(children.len(), 2);
+    assert_eq!(children[0]["name"], "Load order 1");
+    assert_eq!(children[0]["steps"].as_array().unwrap().len(), 1);
+    assert_eq!(children[1]["name"], "Reserve stock");
+    assert_eq!(children[1]["steps"].as_array().unwrap().len(), 3);
+}
+
+#[test]
+#[serial]
+fn test_panic_in_async_step_fails_the_step() {
+    #[allure_test]
+    async fn declined_checkout() {
+        charge_card().await;
+    }
+
+    let results = with_results_dir(|| {
+        let joined =
+            multi_thread_runtime().block_on(async { tokio::spawn(declined_checkout()).await });
+        assert!(joined.unwrap_err().is_panic());
+    });
+
+    assert_eq!(results[0]["status"], "failed");
+    let step = &results[0]["steps"][0];
+    assert_eq!(step["name"], "Charge card");
+    assert_eq!(step["status"], "failed");
+    assert_eq!(step["statusDetails"]["message"], "card declined");
+}
+
+#[allure_test]
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn test_tokio_test_attribute() {
+    tokio::task::yield_now().await;
+    allure_step!("Check refund", {
+        assert_eq!(2 + 2, 4);
+    });
+}
+
+#[allure_test]
+#[async_std::test]
+#[serial]
+async fn test_async_std_test_attribute() {
+    async_std::task::yield_now().await;
+}
+
+struct OrdersClient;
+
+#[allure_steps]
+impl OrdersClient {
+    pub async fn fetch_order(&self, id: u32) -> u32 {
+        tokio::task::yield_now().await;
+        id
+    }
+}
+
+#[test]
+#[serial]
+fn test_allure_steps_wraps_async_methods() {
+    #[allure_test]
+    async fn fetch() {
+        assert_eq!(OrdersClient.fetch_order(3).await, 3);
+    }
+
+    let results = with_results_dir(|| {
+        multi_thread_runtime()
+            .block_on(async { tokio::spawn(fetch()).await })
+            .unwrap();
+    });
+
+    let step = &results[0]["steps"][0];
+    assert_eq!(step["name"], "Fetch order");
+    assert_eq!(step["parameters"][0]["value"], "3");
+}
+
+#[allure_suite("Async Suite", feature = "Async")]
+mod async_suite {
+    use super::*;
+
+    #[allure_test]
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial]
+    async fn keeps_tokio_test() {
+        tokio::task::yield_now().await;
+        allure_step!("Check refund", {});
+    }
+
+    #[test]
+    #[serial]
+    fn test_suite_keeps_runtime_attributes() {
+        let results = with_results_dir(keeps_tokio_test);
+
+        assert_eq!(results[0]["status"], "passed");
+        assert!(has_label(&results[0], "suite", "Async Suite"));
+        assert!(has_label(&results[0], "feature", "Async"));
+        assert_eq!(results[0]["steps"][0]["name"], "Check refund");
+    }
+}
@@ -0,0 +1,83 @@
+#![allow(dead_code)]
+
+use serde_json::Value;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use tempfile::TempDir;
+
+const RESULTS_DIR: &str = "ALLURE_RESULTS_DIR";
+
+/// Points `ALLURE_RESULTS_DIR` at a directory and puts the previous value back
+/// when dropped, even if the test panics.
+pub struct ResultsDir(Option<OsString>);
+
+impl ResultsDir {
+    pub fn enter(dir: &TempDir) -> Self {
+        let previous = env::var_os(RESULTS_DIR);
+        env::set_var(RESULTS_DIR, dir.path());
+        ResultsDir(previous)
+    }
+}
+
+impl Drop for ResultsDir {
+    fn drop(&mut self) {
+        match self.0.take() {
+            Some(previous) => env::set_var(RESULTS_DIR, previous),
+            None => env::remove_var(RESULTS_DIR),
+        }
+    }
+}
+
+pub fn read_results(dir: &TempDir) -> Vec<Value> {
+    read_files(dir, "-result.json")
+}
+
+pub fn read_containers(dir: &TempDir) -> Vec<Value> {
+    read_files(dir, "-container.json")
+}
+
+fn read_files(dir: &TempDir, suffix: &str) -> Vec<Value> {
+    fs::read_dir(dir.path())
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.to_string_lossy().ends_with(suffix))
+        .map(|p| serde_json::from_str(&fs::read_to_string(p).unwrap()).unwrap())
+        .collect()
+}
+
+/// Runs `f` with `ALLURE_RESULTS_DIR` pointing to a fresh directory and
+/// returns the test results written to it.
+pub fn with_results_dir(f: impl FnOnce()) -> Vec<Value> {
+    let temp_dir = TempDir::new().unwrap();
+    let _results_dir = ResultsDir::enter(&temp_dir);
+    f();
+    read_results(&temp_dir)
+}
+
+/// Like [`with_results_dir`], also returning the containers written.
+pub fn with_containers(f: impl FnOnce()) -> (Vec<Value>, Vec<Value>) {
+    let temp_dir = TempDir::new().unwrap();
+    let _results_dir = ResultsDir::enter(&temp_dir);
+    f();
+    (read_results(&temp_dir), read_containers(&temp_dir))
+}
+
+fn labels(result: &Value) -> Vec<(String, String)> {
+    result["labels"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| {
+            (
+                l["name"].as_str().unwrap().to_string(),
+                l["value"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+pub fn has_label(result: &Value, name: &str, value: &str) -> bool {
+    labels(result).iter().any(|(n, v)| n == name && v == value)
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use allure_rust::{allure_data, allure_suite, allure_test};
+use common::{has_label, with_results_dir};
+use serial_test::serial;
+
+#[allure_test("Refund from CSV", feature = "Refunds")]
+#[allure_data("tests/data/refunds.csv")]
+#[serial]
+fn refund_from_csv(amount: u32, currency: &str, partial: bool) {
+    assert!(amount > 0 && currency.len() == 3);
+    let _ = partial;
+}
+
+#[allure_data("tests/data/refunds.json")]
+#[serial]
+fn refund_from_json(amount: u64, currency: String) {
+    assert!(amount > 0 && currency.len() == 3);
+}
+
+#[allure_data("tests/data/refunds.yaml")]
+#[allure_test]
+#[serial]
+#[tokio::test]
+async fn refund_from_yaml(amount: u32, currency: &str) {
+    tokio::task::yield_now().await;
+    assert!(amount > 0 && currency.len() == 3);
+}
+
+#[allure_data("tests/data/refunds.csv")]
+#[serial]
+#[ignore = "refunds are frozen"]
+fn frozen_refund(currency: &str) {
+    assert_eq!(currency.len(), 3);
+}
+
+#[allure_suite("Data Suite")]
+mod data_suite {
+    use super::*;
+
+    #[allure_data("tests/data/refunds.csv")]
+    #[serial]
+    pub fn refund_in_suite(currency: &str) {
+        assert_eq!(currency.len(), 3);
+    }
+}
+
+#[test]
+#[serial]
+fn test_data_row_is_its_own_result() {
+    let first = with_results_dir(|| refund_from_csv("10", "EUR", "true"));
+    let second = with_results_dir(|| refund_from_csv("25", "USD", "false"));
+    let (first, second) = (&first[0], &second[0]);
+
+    assert_eq!(first["name"], "Refund from CSV");
+    assert!(has_label(first, "feature", "Refunds"));
+    let parameters = first["parameters"].as_array().unwrap();
+    let names: Vec<_> = parameters
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["amount", "currency", "partial"]);
+    assert_eq!(parameters[0]["value"], "10");
+    assert_eq!(parameters[1]["value"], "\"EUR\"");
+    assert_eq!(first["testCaseId"], second["testCaseId"]);
+    assert_ne!(first["historyId"], second["historyId"]);
+
+    let attachment = &first["attachments"][0];
+    assert_eq!(attachment["name"], "refunds.csv");
+    assert_eq!(attachment["type"], "text/csv");
+}
+
+#[test]
+#[serial]
+fn test_data_from_json_and_yaml() {
+    let results = with_results_dir(|| refund_from_json("25", "USD"));
+    assert_eq!(results[0]["parameters"][1]["value"], "\"USD\"");
+    assert_eq!(results[0]["attachments"][0]["type"], "application/json");
+
+    let results = with_results_dir(|| {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(refund_from_yaml("10", "EUR"));
+    });
+    assert_eq!(results[0]["status"], "passed");
+    assert_eq!(results[0]["attachments"][0]["name"], "refunds.yaml");
+}
+
+#[test]
+#[serial]
+fn test_column_that_does_not_parse_is_broken() {
+    let results = with_results_dir(|| {
+        let result = std::panic::catch_unwind(|| refund_from_csv("ten", "EUR", "true"));
+        assert!(result.is_err());
+    });
+
+    assert_eq!(results[0]["name"], "Refund from CSV");
+    assert_eq!(results[0]["status"], "broken");
+    assert_eq!(
+        results[0]["statusDetails"]["message"],
+        "can't parse argument `amount` from \"ten\" in tests/data/refunds.csv"
+    );
+    assert_eq!(results[0]["attachments"][0]["name"], "refunds.csv");
+}
+
+#[test]
+#[serial]
+fn test_data_test_in_suite() {
+    let results = with_results_dir(|| data_suite::refund_in_suite("EUR"));
+    assert!(has_label(&results[0], "suite", "Data Suite"));
+}
+
+#[test]
+#[serial]
+fn test_ignored_data_rows_are_reported_one_by_one() {
+    let results = with_results_dir(|| {
+        let args = ["--exact", "frozen_refund::row_2"].map(String::from);
+        allure_rust::ignored::report_selected(args);
+    });
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["status"], "skipped");
+    assert_eq!(results[0]["statusDetails"]["message"], "refunds are frozen");
+}
@@ -0,0 +1,275 @@
+mod common;
+
+use allure_rust::{add_attachment, allure_fixture, allure_step, allure_test, fixture};
+use common::with_containers;
+use rstest::{fixture, rstest};
+use serial_test::serial;
+
+struct Database {
+    name: String,
+}
+
+/// Starts an empty database.
+#[allure_fixture("Start database {name}")]
+fn start_database(name: &str) -> Database {
+    allure_step!("Run migrations", {});
+    Database {
+        name: name.to_string(),
+    }
+}
+
+#[allure_fixture(teardown)]
+fn stop_database(database: &Database) {
+    add_attachment("database", database.name.as_str());
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        stop_database(self);
+    }
+}
+
+#[allure_fixture]
+fn broken_setup() {
+    panic!("database is down");
+}
+
+#[allure_test("Refund with fixtures")]
+#[serial]
+fn refund_with_fixtures() {
+    let database = start_database("refunds");
+    allure_step!("Refund", {
+        assert_eq!(database.name, "refunds");
+    });
+}
+
+#[allure_test]
+#[serial]
+fn refund_with_guards() {
+    let setup = fixture::setup("Seed payments");
+    allure_step!("Insert payment", {});
+    setup.finish();
+
+    allure_step!("Refund", {});
+
+    let _teardown = fixture::teardown("Remove payments");
+    allure_step!("Delete payment", {});
+}
+
+#[allure_test]
+#[serial]
+fn refund_with_broken_setup() {
+    let result = std::panic::catch_unwind(broken_setup);
+    assert!(result.is_err());
+}
+
+#[fixture]
+#[allure_fixture("Open database")]
+fn database() -> Database {
+    Database {
+        name: "rstest".to_string(),
+    }
+}
+
+#[allure_test]
+#[rstest]
+#[serial]
+fn refund_with_rstest_fixture(database: Database) {
+    assert_eq!(database.name, "rstest");
+}
+
+// What rstest runs: the fixture is resolved before the test starts
+#[allure_test]
+fn refund_with_database(database: Database) {
+    assert_eq!(database.name, "rstest");
+}
+
+#[allure_fixture]
+async fn connect() -> u32 {
+    tokio::task::yield_now().await;
+    allure_step!("Handshake", {});
+    7
+}
+
+#[allure_test]
+async fn refund_with_async_fixture() {
+    assert_eq!(connect().await, 7);
+}
+
+#[allure_test]
+async fn refund_next_to_async_fixture() {
+    let other = allure_step!("Other step", async {
+        tokio::task::yield_now().await;
+    });
+    let (connection, _) = tokio::join!(connect(), other);
+    assert_eq!(connection, 7);
+}
+
+#[allure_fixture]
+fn open_ledger() -> Result<(), std::io::Error> {
+    allure_step!("Read ledger", {});
+    Err(std::io::Error::other("ledger is locked"))
+}
+
+#[allure_test]
+#[serial]
+fn refund_with_failing_setup() {
+    assert!(open_ledger().is_err());
+}
+
+fn names(fixtures: &serde_json::Value) -> Vec<&str> {
+    fixtures
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|fixture| fixture["name"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+#[serial]
+fn test_fixtures_go_to_a_container_of_the_test() {
+    let (results, containers) = with_containers(refund_with_fixtures);
+    let (result, container) = (&results[0], &containers[0]);
+
+    assert_eq!(container["children"][0], result["uuid"]);
+    assert_eq!(container["name"], "Refund with fixtures");
+    assert_eq!(names(&container["befores"]), ["Start database refunds"]);
+    assert_eq!(names(&container["afters"]), ["Stop database"]);
+
+    let setup = &container["befores"][0];
+    assert_eq!(setup["status"], "passed");
+    assert_eq!(setup["stage"], "finished");
+    assert_eq!(setup["description"], "Starts an empty database.");
+    assert_eq!(setup["steps"][0]["name"], "Run migrations");
+    assert_eq!(container["afters"][0]["attachments"][0]["name"], "database");
+
+    // The steps of the fixtures are not steps of the test
+    assert_eq!(names(&result["steps"]), ["Refund"]);
+}
+
+#[test]
+#[serial]
+fn test_fixture_guards() {
+    let (results, containers) = with_containers(refund_with_guards);
+    let container = &containers[0];
+
+    assert_eq!(container["befores"][0]["name"], "Seed payments");
+    assert_eq!(
+        container["befores"][0]["steps"][0]["name"],
+        "Insert payment"
+    );
+    assert_eq!(container["afters"][0]["name"], "Remove payments");
+    assert_eq!(container["afters"][0]["steps"][0]["name"], "Delete payment");
+    assert_eq!(names(&results[0]["steps"]), ["Refund"]);
+}
+
+#[test]
+#[serial]
+fn test_panicking_fixture_is_broken() {
+    let (results, containers) = with_containers(refund_with_broken_setup);
+    let setup = &containers[0]["befores"][0];
+
+    assert_eq!(setup["name"], "Broken setup");
+    assert_eq!(setup["status"], "broken");
+    assert_eq!(setup["statusDetails"]["message"], "database is down");
+    assert_eq!(results[0]["status"], "passed");
+}
+
+#[test]
+#[serial]
+fn test_setup_before_test_start_is_taken_over() {
+    let (results, containers) = with_containers(|| refund_with_database(database()));
+
+    assert_eq!(containers[0]["children"][0], results[0]["uuid"]);
+    assert_eq!(names(&containers[0]["befores"]), ["Open database"]);
+    assert_eq!(names(&containers[0]["afters"]), ["Stop database"]);
+}
+
+#[test]
+#[serial]
+fn test_async_fixture() {
+    let (_, containers) = with_containers(|| {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(refund_with_async_fixture());
+    });
+    let setup = &containers[0]["befores"][0];
+
+    assert_eq!(setup["name"], "Connect");
+    assert_eq!(setup["steps"][0]["name"], "Handshake");
+}
+
+#[test]
+#[serial]
+fn test_async_fixture_is_its_own_flow() {
+    let (results, containers) = with_containers(|| {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .build()
+            .unwrap();
+        runtime.block_on(refund_next_to_async_fixture());
+    });
+    let setup = &containers[0]["befores"][0];
+
+    assert_eq!(setup["name"], "Connect");
+    assert_eq!(names(&setup["steps"]), ["Handshake"]);
+    assert_eq!(names(&results[0]["steps"]), ["Other step"]);
+}
+
+#[test]
+#[serial]
+fn test_fixture_returning_err_is_broken() {
+    let (results, containers) = with_containers(refund_with_failing_setup);
+    let setup = &containers[0]["befores"][0];
+
+    assert_eq!(setup["name"], "Open ledger");
+    assert_eq!(setup["status"], "broken");
+    assert_eq!(setup["statusDetails"]["message"], "ledger is locked");
+    assert_eq!(setup["steps"][0]["name"], "Read ledger");
+    assert_eq!(results[0]["status"], "passed");
+}
+
+#[test]
+#[serial]
+fn test_setup_of_a_running_test_is_not_taken_over() {
+    let (results, containers) = with_containers(|| {
+        allure_rust::start_test("first");
+        fixture::setup("Seed payments").finish();
+        allure_rust::start_test("second");
+        allure_rust::end_test("second", Ok(()));
+    });
+
+    assert_eq!(results[0]["name"], "second");
+    assert!(containers.is_empty());
+}
+
+#[test]
+#[serial]
+fn test_fixture_finished_in_another_test_is_recorded() {
+    let (results, containers) = with_containers(|| {
+        allure_rust::start_test("first");
+        let setup = fixture::setup("Seed payments");
+        allure_rust::start_test("second");
+        setup.finish();
+        allure_rust::end_test("second", Ok(()));
+    });
+
+    let marker = &results[0]["steps"][0];
+    assert_eq!(marker["name"], "Fixture finished in another test");
+    assert_eq!(marker["status"], "broken");
+    assert!(containers.is_empty());
+}
+
+#[test]
+#[serial]
+fn test_no_container_without_fixtures() {
+    let (results, containers) = with_containers(|| {
+        allure_rust::start_test("no fixtures");
+        allure_rust::end_test("no fixtures", Ok(()));
+    });
+
+    assert_eq!(results.len(), 1);
+    assert!(containers.is_empty());
+}
@@ -0,0 +1,748 @@
+mod common;
+
+use allure_rust::{allure_step, allure_steps, allure_suite, allure_test, step};
+use common::{has_label, with_results_dir};
+use serial_test::serial;
+
+#[test]
+#[serial]
+fn test_allure_test_static_metadata() {
+    #[allure_test(
+        title = "Refund a payment",
+        epic = "Billing",
+        feature = "Refunds",
+        story = "Partial refund",
+        severity = critical,
+        owner = "team-pay",
+        tags("smoke", "regression"),
+        issue = "PAY-12",
+        link = "https://docs.example.com"
+    )]
+    fn refund() {}
+
+    let results = with_results_dir(refund);
+
+    let result = &results[0];
+    assert_eq!(result["name"], "Refund a payment");
+    assert!(has_label(result, "epic", "Billing"));
+    assert!(has_label(result, "feature", "Refunds"));
+    assert!(has_label(result, "story", "Partial refund"));
+    assert!(has_label(result, "severity", "critical"));
+    assert!(has_label(result, "owner", "team-pay"));
+    assert!(has_label(result, "tag", "smoke"));
+    assert!(has_label(result, "tag", "regression"));
+    assert_eq!(result["links"][0]["type"], "issue");
+    assert_eq!(result["links"][0]["name"], "PAY-12");
+    assert_eq!(result["links"][1]["url"], "https://docs.example.com");
+}
+
+#[test]
+#[serial]
+fn test_allure_test_bare_title_with_metadata() {
+    #[allure_test("Bare title", severity = "minor")]
+    fn bare_title() {}
+
+    let results = with_results_dir(bare_title);
+
+    assert_eq!(results[0]["name"], "Bare title");
+    assert!(has_label(&results[0], "severity", "minor"));
+}
+
+#[test]
+#[serial]
+fn test_doc_comments_become_descriptions() {
+    /// Creates the user.
+    ///
+    /// Uses the **admin** API.
+    #[step("Create user")]
+    fn create_user() {}
+
+    /// Checks that a user can be created.
+    #[allure_test]
+    fn documented() {
+        create_user();
+    }
+
+    let results = with_results_dir(documented);
+
+    let result = &results[0];
+    assert_eq!(result["description"], "Checks that a user can be created.");
+    assert_eq!(
+        result["descriptionHtml"],
+        "<p>Checks that a user can be created.</p>\n"
+    );
+    let step = &result["steps"][0];
+    assert_eq!(
+        step["description"],
+        "Creates the user.\n\nUses the **admin** API."
+    );
+    assert!(
+        step["descriptionHtml"]
+            .as_str()
+            .unwrap()
+            .contains("<strong>admin</strong>")
+    );
+}
+
+#[test]
+#[serial]
+fn test_explicit_description_overrides_doc_comment() {
+    /// Doc comment
+    #[allure_test(description = "Explicit")]
+    fn overridden() {}
+
+    let results = with_results_dir(overridden);
+
+    assert_eq!(results[0]["description"], "Explicit");
+}
+
+#[test]
+#[serial]
+fn test_step_status_follows_panic_origin() {
+    #[step("Broken step")]
+    fn broken_step() {
+        "no connection".parse::<i32>().unwrap();
+    }
+
+    #[step("Failed step")]
+    fn failed_step() {
+        assert_eq!(1, 2);
+    }
+
+    #[allure_test]
+    fn with_broken_step() {
+        broken_step();
+    }
+
+    #[allure_test]
+    fn with_failed_step() {
+        failed_step();
+    }
+
+    let results = with_results_dir(|| {
+        let _ = std::panic::catch_unwind(with_broken_step);
+    });
+    assert_eq!(results[0]["status"], "broken");
+    assert_eq!(results[0]["steps"][0]["status"], "broken");
+
+    let results = with_results_dir(|| {
+        let _ = std::panic::catch_unwind(with_failed_step);
+    });
+    assert_eq!(results[0]["status"], "failed");
+    assert_eq!(results[0]["steps"][0]["status"], "failed");
+}
+
+#[test]
+#[serial]
+fn test_step_keeps_the_panic_payload() {
+    #[step("Reject refund")]
+    fn reject_refund() {
+        std::panic::panic_any(RefundError {
+            source: std::io::Error::other("card expired"),
+        });
+    }
+
+    #[allure_test]
+    fn with_rejected_refund() {
+        reject_refund();
+    }
+
+    let mut payload = None;
+    let results = with_results_dir(|| {
+        payload = std::panic::catch_unwind(with_rejected_refund).err();
+    });
+
+    let step = &results[0]["steps"][0];
+    assert_eq!(step["status"], "broken");
+    assert_eq!(step["statusDetails"]["message"], "Step panicked");
+    // The payload reaches the caller as it was raised
+    let error = payload.unwrap().downcast::<RefundError>().unwrap();
+    assert_eq!(error.source.to_string(), "card expired");
+}
+
+#[test]
+#[serial]
+fn test_allure_step_returns_the_block_value() {
+    #[allure_test]
+    fn create_and_login() {
+        let user = allure_step!("Create user", { "alice".to_string() });
+        let attempts = 2;
+        let session = allure_step!("Login as {} after {} attempts", user, attempts, {
+            format!("session-{}", user)
+        });
+        assert_eq!(session, "session-alice");
+    }
+
+    let results = with_results_dir(create_and_login);
+
+    let steps = &results[0]["steps"];
+    assert_eq!(steps[0]["name"], "Create user");
+    assert_eq!(steps[1]["name"], "Login as alice after 2 attempts");
+    assert_eq!(steps[1]["status"], "passed");
+}
+
+#[test]
+#[serial]
+fn test_allure_step_err_marks_the_step_broken() {
+    #[allure_test]
+    fn with_err_steps() {
+        let parsed: Result<u16, _> = allure_step!("Parse port", { "http".parse::<u16>() });
+        assert!(parsed.is_err());
+        let checked: Result<(), String> =
+            allure_step!("Check port", { Err("port is closed".to_string()) });
+        assert!(checked.is_err());
+        let ok = allure_step!("Default port", { "80".parse::<u16>() });
+        assert_eq!(ok, Ok(80));
+    }
+
+    let results = with_results_dir(with_err_steps);
+
+    let steps = &results[0]["steps"];
+    assert_eq!(steps[0]["status"], "broken");
+    assert_eq!(
+        steps[0]["statusDetails"]["message"],
+        "invalid digit found in string"
+    );
+    assert_eq!(steps[1]["status"], "broken");
+    assert_eq!(steps[1]["statusDetails"]["message"], "\"port is closed\"");
+    assert_eq!(steps[2]["status"], "passed");
+    // The error is handed back to the test, which decides whether it fails
+    assert_eq!(results[0]["status"], "passed");
+}
+
+#[step("Parse port {text}")]
+fn parse_port(text: &str) -> Result<u16, std::num::ParseIntError> {
+    let port = text.parse()?;
+    Ok(port)
+}
+
+#[test]
+#[serial]
+fn test_step_fn_returning_err_is_broken() {
+    #[allure_test]
+    fn with_step_fns() {
+        assert!(parse_port("http").is_err());
+        assert_eq!(parse_port("80"), Ok(80));
+    }
+
+    let results = with_results_dir(with_step_fns);
+
+    let steps = &results[0]["steps"];
+    assert_eq!(steps[0]["name"], "Parse port http");
+    assert_eq!(steps[0]["status"], "broken");
+    assert_eq!(
+        steps[0]["statusDetails"]["message"],
+        "invalid digit found in string"
+    );
+    assert_eq!(steps[1]["status"], "passed");
+    assert_eq!(results[0]["status"], "passed");
+}
+
+#[derive(Debug)]
+struct Account {
+    id: &'static str,
+    balance: (u32, &'static str),
+}
+
+impl Account {
+    #[step("Check {self.id} holds {minimum} {self.balance.1}")]
+    fn check_balance(&self, minimum: u32) -> bool {
+        self.balance.0 >= minimum
+    }
+}
+
+#[step("Transfer {amount:.2} from {from.id} to {to.id} {{{note:?}}}")]
+fn transfer(amount: f64, from: &Account, to: &Account, note: &str) {}
+
+#[test]
+#[serial]
+fn test_step_title_interpolates_arguments() {
+    #[allure_test]
+    fn transfer_funds() {
+        let alice = Account {
+            id: "alice",
+            balance: (100, "EUR"),
+        };
+        let bob = Account {
+            id: "bob",
+            balance: (0, "EUR"),
+        };
+        assert!(alice.check_balance(25));
+        transfer(25.0, &alice, &bob, "rent");
+    }
+
+    let results = with_results_dir(transfer_funds);
+
+    let steps = &results[0]["steps"];
+    assert_eq!(steps[0]["name"], "Check alice holds 25 EUR");
+    assert_eq!(
+        steps[1]["name"],
+        "Transfer 25.00 from alice to bob {\"rent\"}"
+    );
+    assert_eq!(steps[1]["parameters"][0]["name"], "amount");
+}
+
+struct Session {
+    user: String,
+}
+
+impl Session {
+    #[step("Open {page}", display(self))]
+    fn open(&self, page: &str) {}
+
+    #[step]
+    fn close(self) {}
+}
+
+impl std::fmt::Display for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "session of {}", self.user)
+    }
+}
+
+#[step(
+    "Login as {user} with {password}, {token:?} and {trace_id}",
+    skip(password),
+    mask(token),
+    hide(trace_id),
+    exclude(attempt),
+    display(user),
+    debug(attempt)
+)]
+fn login(user: &str, password: &str, token: &str, trace_id: u64, attempt: u32) {
+    assert_ne!(password, token);
+}
+
+#[step]
+fn move_money((from, to): (&str, &str), Account { id, .. }: &Account, session: &Session) {}
+
+#[step(skip_all)]
+fn quiet(secret: &str) {
+    assert!(!secret.is_empty());
+}
+
+#[test]
+#[serial]
+fn test_step_parameter_options() {
+    #[allure_test]
+    fn login_and_move() {
+        login("alice", "hunter2", "t0k3n", 42, 1);
+        let session = Session {
+            user: "alice".to_string(),
+        };
+        session.open("accounts");
+        let account = Account {
+            id: "alice",
+            balance: (1, "EUR"),
+        };
+        move_money(("alice", "bob"), &account, &session);
+        quiet("hunter2");
+        session.close();
+    }
+
+    let results = with_results_dir(login_and_move);
+
+    let steps = &results[0]["steps"];
+    // Skipped, masked and hidden values stay out of the title
+    assert_eq!(
+        steps[0]["name"],
+        "Login as alice with ******, ****** and ******"
+    );
+    let login = steps[0]["parameters"].as_array().unwrap();
+    assert_eq!(login.len(), 4);
+    assert_eq!(login[0]["name"], "user");
+    assert_eq!(login[0]["value"], "alice");
+    assert!(login[0].get("mode").is_none());
+    assert_eq!(login[1]["name"], "token");
+    assert_eq!(login[1]["value"], "******");
+    assert_eq!(login[1]["mode"], "masked");
+    assert_eq!(login[2]["name"], "trace_id");
+    assert_eq!(login[2]["value"], "42");
+    assert_eq!(login[2]["mode"], "hidden");
+    assert_eq!(login[3]["name"], "attempt");
+    assert_eq!(login[3]["value"], "1");
+    assert_eq!(login[3]["excluded"], true);
+
+    assert_eq!(steps[1]["name"], "Open accounts");
+    assert_eq!(steps[1]["parameters"][0]["name"], "self");
+    assert_eq!(steps[1]["parameters"][0]["value"], "session of alice");
+
+    let moved = steps[2]["parameters"].as_array().unwrap();
+    let names: Vec<_> = moved.iter().map(|p| p["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["from", "to", "id", "session"]);
+    assert_eq!(moved[1]["value"], "\"bob\"");
+    // Arguments that are not `Debug` are recorded by their type
+    assert!(moved[3]["value"].as_str().unwrap().ends_with("Session"));
+
+    assert!(steps[3]["parameters"].as_array().unwrap().is_empty());
+    assert!(steps[4]["parameters"].as_array().unwrap().is_empty());
+}
+
+struct CartPage {
+    items: Vec<String>,
+}
+
+#[allure_steps]
+impl CartPage {
+    pub fn new() -> Self {
+        CartPage { items: Vec::new() }
+    }
+
+    pub fn add_to_cart(&mut self, item: &str) {
+        self.normalize();
+        self.items.push(item.to_string());
+    }
+
+    #[step("Pay with {card}", mask(card))]
+    pub fn pay(&self, card: &str) -> usize {
+        assert!(!card.is_empty());
+        self.items.len()
+    }
+
+    #[no_step]
+    pub fn item_count(&self) -> usize {
+        self.items.len()
+    }
+
+    fn normalize(&mut self) {
+        self.items.retain(|item| !item.is_empty());
+    }
+}
+
+#[allure_steps]
+trait Page {
+    fn url(&self) -> String;
+
+    fn open_page(&self) -> String {
+        self.url()
+    }
+}
+
+#[allure_steps]
+impl Page for CartPage {
+    fn url(&self) -> String {
+        "/cart".to_string()
+    }
+}
+
+#[test]
+#[serial]
+fn test_allure_steps_wraps_methods() {
+    #[allure_test]
+    fn checkout_page() {
+        let mut page = CartPage::new();
+        page.add_to_cart("book");
+        assert_eq!(page.item_count(), 1);
+        assert_eq!(page.pay("4111"), 1);
+        assert_eq!(page.open_page(), "/cart");
+    }
+
+    let results = with_results_dir(checkout_page);
+
+    let steps = results[0]["steps"].as_array().unwrap();
+    let names: Vec<_> = steps.iter().map(|s| s["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["Add to cart", "Pay with ******", "Open page"]);
+    assert!(!results[0].to_string().contains("4111"));
+    assert_eq!(steps[0]["parameters"][0]["name"], "item");
+    assert!(steps[0]["steps"].as_array().unwrap().is_empty());
+    assert_eq!(steps[1]["parameters"][0]["mode"], "masked");
+    assert_eq!(steps[2]["steps"][0]["name"], "Url");
+}
+
+#[derive(Debug)]
+struct RefundError {
+    source: std::io::Error,
+}
+
+impl std::fmt::Display for RefundError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "refund failed")
+    }
+}
+
+impl std::error::Error for RefundError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+#[test]
+#[serial]
+fn test_result_returning_tests() {
+    #[allure_test]
+    fn returns_ok() -> Result<(), Box<dyn std::error::Error>> {
+        let amount: i32 = "42".parse()?;
+        assert_eq!(amount, 42);
+        Ok(())
+    }
+
+    #[allure_test]
+    fn returns_boxed_err() -> Result<(), Box<dyn std::error::Error>> {
+        let _amount: i32 = "forty two".parse()?;
+        Ok(())
+    }
+
+    #[allure_test]
+    fn returns_err_with_source() -> Result<(), RefundError> {
+        Err(RefundError {
+            source: std::io::Error::other("gateway timeout"),
+        })
+    }
+
+    #[allure_test]
+    fn returns_debug_err() -> Result<u8, String> {
+        Err("no refunds today".to_string())
+    }
+
+    let mut value = None;
+    let results = with_results_dir(|| value = Some(returns_ok()));
+    assert!(value.unwrap().is_ok());
+    assert_eq!(results[0]["status"], "passed");
+
+    let mut value = None;
+    let results = with_results_dir(|| value = Some(returns_boxed_err()));
+    assert!(value.unwrap().is_err());
+    assert_eq!(results[0]["status"], "broken");
+    assert_eq!(
+        results[0]["statusDetails"]["message"],
+        "invalid digit found in string"
+    );
+
+    let results = with_results_dir(|| {
+        let _ = returns_err_with_source();
+    });
+    assert_eq!(results[0]["statusDetails"]["message"], "refund failed");
+    let trace = results[0]["statusDetails"]["trace"].as_str().unwrap();
+    assert!(trace.contains("Caused by:\n    0: gateway timeout"));
+
+    let mut value = None;
+    let results = with_results_dir(|| value = Some(returns_debug_err()));
+    assert_eq!(value.unwrap(), Err("no refunds today".to_string()));
+    assert_eq!(results[0]["status"], "broken");
+    assert_eq!(
+        results[0]["statusDetails"]["message"],
+        "\"no refunds today\""
+    );
+}
+
+#[test]
+#[serial]
+fn test_original_panic_payload_is_re_raised() {
+    #[allure_test]
+    fn failing() {
+        assert_eq!(1 + 1, 3, "math is broken");
+    }
+
+    let mut payload = None;
+    let results = with_results_dir(|| payload = std::panic::catch_unwind(failing).err());
+
+    let payload = payload.unwrap();
+    let message = payload.downcast_ref::<String>().unwrap();
+    assert!(message.contains("math is broken"));
+    assert!(message.contains("left: 2"));
+    assert_eq!(results[0]["statusDetails"]["message"], message.as_str());
+}
+
+#[allure_test]
+#[test]
+#[serial]
+#[should_panic(expected = "amount too large")]
+fn test_should_panic_keeps_expected_message() {
+    panic!("refund rejected: amount too large");
+}
+
+#[allure_test(title = "Ignored test", feature = "Refunds")]
+#[test]
+#[serial]
+#[ignore = "waiting for the sandbox"]
+fn ignored_refund() {
+    allure_step!("Refund", {});
+}
+
+#[allure_test]
+#[test]
+#[serial]
+#[ignore]
+#[should_panic(expected = "sandbox is down")]
+fn ignored_panicking_refund() {
+    panic!("sandbox is down");
+}
+
+fn report_ignored(args: &[&str]) -> Vec<serde_json::Value> {
+    with_results_dir(|| {
+        allure_rust::ignored::report_selected(args.iter().map(|arg| arg.to_string()))
+    })
+}
+
+#[test]
+#[serial]
+fn test_ignored_tests_are_reported_as_skipped_on_request() {
+    let results = report_ignored(&["--exact", "ignored_refund"]);
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["name"], "Ignored test");
+    assert_eq!(results[0]["status"], "skipped");
+    assert_eq!(
+        results[0]["statusDetails"]["message"],
+        "waiting for the sandbox"
+    );
+    assert!(has_label(&results[0], "feature", "Refunds"));
+    assert!(has_label(&results[0], "testMethod", "ignored_refund"));
+
+    let results = report_ignored(&["ignored_", "--skip=ignored_refund"]);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["name"], "ignored_panicking_refund");
+    assert_eq!(results[0]["statusDetails"]["message"], "Test is ignored");
+
+    assert!(report_ignored(&["test_metadata"]).is_empty());
+}
+
+// When libtest runs them, ignored tests report themselves like any other
+#[test]
+#[serial]
+fn test_ignored_tests_that_run_are_not_skipped() {
+    assert!(report_ignored(&["--include-ignored"]).is_empty());
+    assert!(report_ignored(&["--ignored", "ignored_refund"]).is_empty());
+
+    let results = with_results_dir(ignored_refund);
+    assert_eq!(results[0]["status"], "passed");
+    assert_eq!(results[0]["steps"][0]["name"], "Refund");
+
+    let results = with_results_dir(|| {
+        let result = std::panic::catch_unwind(ignored_panicking_refund);
+        assert!(result.is_err());
+    });
+    assert_eq!(results[0]["status"], "passed");
+}
+
+#[allure_suite("Metadata Suite")]
+mod suite_metadata {
+    use super::*;
+
+    #[allure_test(title = "Suite test", feature = "Suites")]
+    #[serial]
+    fn suite_test() {}
+
+    #[test]
+    #[serial]
+    fn test_suite_keeps_test_metadata() {
+        let results = with_results_dir(suite_test);
+
+        assert_eq!(results[0]["name"], "Suite test");
+        assert!(has_label(&results[0], "parentSuite", "macro_tests"));
+        assert!(has_label(&results[0], "suite", "Metadata Suite"));
+        assert!(has_label(&results[0], "feature", "Suites"));
+    }
+
+    #[allure_suite("Nested Suite")]
+    mod nested {
+        use super::*;
+
+        #[allure_suite("Innermost Suite")]
+        mod innermost {
+            use super::*;
+
+            #[allure_test]
+            #[serial]
+            fn nested_test() {}
+
+            #[test]
+            #[serial]
+            fn test_nested_suites_compose_their_names() {
+                let results = with_results_dir(nested_test);
+
+                let result = &results[0];
+                assert!(has_label(result, "suite", "Metadata Suite"));
+                assert!(has_label(
+                    result,
+                    "subSuite",
+                    "Nested Suite / Innermost Suite"
+                ));
+                assert!(has_label(result, "testClass", "innermost"));
+                assert!(has_label(result, "testMethod", "nested_test"));
+            }
+        }
+    }
+}
+
+#[allure_suite(
+    "Refund Suite",
+    epic = "Billing",
+    owner = "team-pay",
+    severity = minor,
+    tags("refunds")
+)]
+mod suite_inheritance {
+    use super::*;
+
+    #[allure_test(severity = critical, tags("smoke"))]
+    #[serial]
+    fn inherits_metadata() {}
+
+    #[allure_test]
+    #[serial]
+    #[should_panic(expected = "refund rejected")]
+    fn keeps_should_panic() {
+        panic!("refund rejected");
+    }
+
+    mod partial {
+        use super::*;
+
+        #[allure_test]
+        #[serial]
+        pub fn in_plain_submodule() {}
+    }
+
+    #[allure_suite("Disputes", feature = "Chargebacks", owner = "team-risk")]
+    mod disputes {
+        use super::*;
+
+        #[allure_test]
+        #[serial]
+        pub fn in_nested_suite() {}
+    }
+
+    fn labels(result: &serde_json::Value, name: &str) -> Vec<String> {
+        result["labels"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|label| label["name"] == name)
+            .map(|label| label["value"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    #[serial]
+    fn test_suite_metadata_is_inherited() {
+        let results = with_results_dir(inherits_metadata);
+        let result = &results[0];
+        assert!(has_label(result, "suite", "Refund Suite"));
+        assert!(has_label(result, "epic", "Billing"));
+        assert!(has_label(result, "owner", "team-pay"));
+        assert_eq!(labels(result, "severity"), ["critical"]);
+        assert_eq!(labels(result, "tag"), ["refunds", "smoke"]);
+
+        let results = with_results_dir(partial::in_plain_submodule);
+        assert!(has_label(&results[0], "suite", "Refund Suite"));
+        assert!(!has_label(&results[0], "subSuite", "partial"));
+        assert!(has_label(&results[0], "epic", "Billing"));
+
+        let results = with_results_dir(disputes::in_nested_suite);
+        let result = &results[0];
+        assert!(has_label(result, "subSuite", "Disputes"));
+        assert!(has_label(result, "epic", "Billing"));
+        assert!(has_label(result, "feature", "Chargebacks"));
+        assert_eq!(labels(result, "owner"), ["team-risk"]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_suite_keeps_should_panic() {
+        let results = with_results_dir(|| {
+            assert!(std::panic::catch_unwind(keeps_should_panic).is_err());
+        });
+        assert_eq!(results[0]["status"], "passed");
+    }
+}
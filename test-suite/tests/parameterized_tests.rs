@@ -0,0 +1,79 @@
+mod common;
+
+use allure_rust::allure_test;
+use common::with_results_dir;
+use rstest::{fixture, rstest};
+use serial_test::serial;
+
+struct Rates {
+    eur_to_usd: f64,
+}
+
+#[fixture]
+fn rates() -> Rates {
+    Rates { eur_to_usd: 1.1 }
+}
+
+#[allure_test("Refund in any currency")]
+#[rstest]
+#[case(10, "EUR")]
+#[case(25, "USD")]
+#[serial]
+fn refund(
+    rates: Rates,
+    #[case] amount: u32,
+    #[case] currency: &str,
+    #[values(true, false)] partial: bool,
+) {
+    assert!(rates.eur_to_usd > 1.0);
+    assert!(amount > 0 && !currency.is_empty());
+    let _ = partial;
+}
+
+#[allure_test]
+#[rstest]
+#[case::positive(3)]
+#[serial]
+#[tokio::test]
+async fn async_refund(#[case] amount: u32) {
+    tokio::task::yield_now().await;
+    assert_eq!(amount, 3);
+}
+
+#[test]
+#[serial]
+fn test_each_case_is_its_own_result() {
+    let first = with_results_dir(|| refund(rates(), 10, "EUR", true));
+    let second = with_results_dir(|| refund(rates(), 25, "USD", true));
+    let (first, second) = (&first[0], &second[0]);
+
+    assert_eq!(first["name"], "Refund in any currency");
+    let parameters = first["parameters"].as_array().unwrap();
+    let names: Vec<_> = parameters
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["amount", "currency", "partial"]);
+    assert_eq!(parameters[0]["value"], "10");
+    assert_eq!(parameters[1]["value"], "\"EUR\"");
+    assert_eq!(parameters[2]["value"], "true");
+
+    // The same test, but a different case of it
+    assert_eq!(first["testCaseId"], second["testCaseId"]);
+    assert_ne!(first["historyId"], second["historyId"]);
+}
+
+#[test]
+#[serial]
+fn test_case_of_async_test() {
+    let results = with_results_dir(|| {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async_refund(3));
+    });
+
+    assert_eq!(results[0]["status"], "passed");
+    assert_eq!(results[0]["parameters"][0]["name"], "amount");
+    assert_eq!(results[0]["parameters"][0]["value"], "3");
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use allure_rust::rayon::ParallelIteratorExt;
+use allure_rust::{allure_step, allure_test, step};
+use common::with_results_dir;
+use rayon::prelude::*;
+use serial_test::serial;
+
+#[step("Validate row")]
+fn validate_row(row: usize) {
+    // Later rows finish first, so start order differs from index order
+    std::thread::sleep(std::time::Duration::from_millis((20 - row as u64) * 2));
+    allure_step!("Check columns", {});
+}
+
+#[test]
+#[serial]
+fn test_rayon_steps_nest_in_index_order() {
+    #[allure_test]
+    fn validate_rows() {
+        allure_step!("Validate rows", {
+            (0..20)
+                .into_par_iter()
+                .with_current_test()
+                .for_each(validate_row);
+        });
+    }
+
+    let results = with_results_dir(validate_rows);
+
+    let parent = &results[0]["steps"][0];
+    assert_eq!(parent["name"], "Validate rows");
+    let rows = parent["steps"].as_array().unwrap();
+    assert_eq!(rows.len(), 20);
+    for (index, row) in rows.iter().enumerate() {
+        assert_eq!(row["parameters"][0]["value"], index.to_string());
+        assert_eq!(row["steps"][0]["name"], "Check columns");
+    }
+}
+
+#[test]
+#[serial]
+fn test_rayon_steps_keep_their_place_among_siblings() {
+    #[allure_test]
+    fn mixed_steps() {
+        allure_step!("Before", {});
+        let sum: usize = vec![3, 1, 2]
+            .par_iter()
+            .with_current_test()
+            .map(|&row| {
+                validate_row(row);
+                row
+            })
+            .sum();
+        assert_eq!(sum, 6);
+        allure_step!("After", {});
+    }
+
+    let results = with_results_dir(mixed_steps);
+
+    let names: Vec<_> = results[0]["steps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|step| {
+            format!(
+                "{} {}",
+                step["name"].as_str().unwrap(),
+                step["parameters"][0]["value"].as_str().unwrap_or("")
+            )
+        })
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "Before ",
+            "Validate row 3",
+            "Validate row 1",
+            "Validate row 2",
+            "After "
+        ]
+    );
+}
+
+#[test]
+#[serial]
+fn test_rayon_join() {
+    #[allure_test]
+    fn joined() {
+        allure_rust::rayon::join(
+            || {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                allure_step!("Left", {});
+            },
+            || allure_step!("Right", {}),
+        );
+    }
+
+    let results = with_results_dir(joined);
+
+    let steps = results[0]["steps"].as_array().unwrap();
+    assert_eq!(steps[0]["name"], "Left");
+    assert_eq!(steps[1]["name"], "Right");
+}
+
+#[test]
+#[serial]
+fn test_rayon_indexed_adapters() {
+    #[allure_test]
+    fn indexed() {
+        let rows: Vec<usize> = (0..20).collect();
+        allure_step!("Enumerate", {
+            rows.par_iter()
+                .with_current_test()
+                .enumerate()
+                .for_each(|(index, &row)| {
+                    assert_eq!(index, row);
+                    validate_row(row);
+                });
+        });
+        allure_step!("Zip", {
+            rows.par_iter()
+                .with_current_test()
+                .zip(rows.par_iter())
+                .for_each(|(&row, _)| validate_row(row));
+        });
+        allure_step!("Collect", {
+            let mut checked = Vec::new();
+            rows.par_iter()
+                .with_current_test()
+                .map(|&row| {
+                    validate_row(row);
+                    row
+                })
+                .collect_into_vec(&mut checked);
+            assert_eq!(checked, rows);
+        });
+    }
+
+    let results = with_results_dir(indexed);
+
+    let steps = results[0]["steps"].as_array().unwrap();
+    assert_eq!(steps.len(), 3);
+    for step in steps {
+        let rows = step["steps"].as_array().unwrap();
+        assert_eq!(rows.len(), 20, "{}", step["name"]);
+        for (index, row) in rows.iter().enumerate() {
+            assert_eq!(row["parameters"][0]["value"], index.to_string());
+        }
+    }
+}
@@ -0,0 +1,434 @@
+mod common;
+
+use allure_rust::assert;
+use allure_rust_core::{
+    Severity, add_attachment, current_test, description, end_step, end_test,
+    end_test_expecting_panic, epic, feature, issue, label, link, owner, parameter, parent_suite,
+    severity, start_step, start_test, start_test_with_context, start_test_with_function, story,
+    sub_suite, tag, tms,
+};
+use common::{has_label, with_results_dir};
+use serde_json::Value;
+use serial_test::serial;
+use std::panic;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+#[serial]
+fn test_result_records_real_duration() {
+    let results = with_results_dir(|| {
+        start_test("Timed test");
+        thread::sleep(Duration::from_millis(50));
+        end_test("Timed test", Ok(()));
+    });
+
+    assert_eq!(results.len(), 1);
+    let start = results[0]["start"].as_i64().unwrap();
+    let stop = results[0]["stop"].as_i64().unwrap();
+    assert!(stop - start >= 50, "duration was {} ms", stop - start);
+}
+
+#[test]
+#[serial]
+fn test_earlier_signatures_still_work() {
+    let results = with_results_dir(|| {
+        start_test_with_context("Earlier", None, Some("crate::module"));
+        end_test("Earlier", Ok(()));
+    });
+
+    assert_eq!(results[0]["name"], "Earlier");
+    assert_eq!(results[0]["status"], "passed");
+    assert!(has_label(&results[0], "package", "crate.module"));
+}
+
+#[test]
+#[serial]
+fn test_ids_are_stable_across_runs() {
+    let results = with_results_dir(|| {
+        for _ in 0..2 {
+            start_test_with_function("Stable", None, Some("crate::module"), Some("stable"));
+            end_test("Stable", Ok(()));
+        }
+        start_test_with_function("Other", None, Some("crate::module"), Some("other"));
+        end_test("Other", Ok(()));
+    });
+
+    let stable: Vec<_> = results
+        .iter()
+        .filter(|r| r["fullName"] == "crate::module::stable")
+        .collect();
+    let other = results
+        .iter()
+        .find(|r| r["fullName"] == "crate::module::other")
+        .unwrap();
+
+    assert_eq!(stable.len(), 2);
+    assert_ne!(stable[0]["uuid"], stable[1]["uuid"]);
+    assert_eq!(stable[0]["historyId"], stable[1]["historyId"]);
+    assert_eq!(stable[0]["testCaseId"], stable[1]["testCaseId"]);
+    assert_ne!(stable[0]["testCaseId"], other["testCaseId"]);
+    assert_ne!(stable[0]["historyId"], other["historyId"]);
+}
+
+#[test]
+#[serial]
+fn test_module_path_is_split_into_suites() {
+    let results = with_results_dir(|| {
+        start_test_with_function(
+            "Suites",
+            None,
+            Some("shop::orders::refunds::tests"),
+            Some("suites"),
+        );
+        end_test("Suites", Ok(()));
+    });
+    let result = &results[0];
+    assert!(has_label(result, "parentSuite", "shop"));
+    assert!(has_label(result, "suite", "orders"));
+    assert!(has_label(result, "subSuite", "refunds.tests"));
+    assert!(has_label(result, "package", "shop.orders.refunds.tests"));
+    assert!(has_label(result, "testClass", "tests"));
+}
+
+#[test]
+#[serial]
+fn test_suites_can_be_replaced_at_runtime() {
+    let results = with_results_dir(|| {
+        start_test_with_function(
+            "Suites",
+            Some("Refunds"),
+            Some("shop::tests"),
+            Some("suites"),
+        );
+        parent_suite("Shop");
+        sub_suite("Partial");
+        end_test("Suites", Ok(()));
+    });
+
+    let result = &results[0];
+    assert!(has_label(result, "parentSuite", "Shop"));
+    assert!(has_label(result, "suite", "Refunds"));
+    assert!(has_label(result, "subSuite", "Partial"));
+    let labels = result["labels"].as_array().unwrap();
+    assert_eq!(
+        labels
+            .iter()
+            .filter(|label| label["name"] == "suite")
+            .count(),
+        1
+    );
+}
+
+#[test]
+#[serial]
+fn test_runtime_metadata_is_written() {
+    std::env::set_var("ALLURE_LINK_ISSUE_PATTERN", "https://issues.example.com/{}");
+    let results = with_results_dir(|| {
+        start_test_with_function("Metadata", None, Some("crate::module"), Some("metadata"));
+        epic("Billing");
+        feature("Refunds");
+        story("Partial refund");
+        severity(Severity::Critical);
+        owner("team-pay");
+        tag("smoke");
+        label("layer", "api");
+        link("Docs", "https://docs.example.com");
+        issue("PAY-12");
+        tms("TC-1");
+        description("Refunds **part** of a payment");
+        parameter("amount", 42);
+        end_test("Metadata", Ok(()));
+    });
+    std::env::remove_var("ALLURE_LINK_ISSUE_PATTERN");
+
+    let result = &results[0];
+    assert!(has_label(result, "parentSuite", "crate"));
+    assert!(has_label(result, "suite", "module"));
+    assert!(has_label(result, "package", "crate.module"));
+    assert!(has_label(result, "testClass", "module"));
+    assert!(has_label(result, "testMethod", "metadata"));
+    assert!(has_label(result, "epic", "Billing"));
+    assert!(has_label(result, "feature", "Refunds"));
+    assert!(has_label(result, "story", "Partial refund"));
+    assert!(has_label(result, "severity", "critical"));
+    assert!(has_label(result, "owner", "team-pay"));
+    assert!(has_label(result, "tag", "smoke"));
+    assert!(has_label(result, "layer", "api"));
+
+    let links = result["links"].as_array().unwrap();
+    assert_eq!(links[0]["type"], "link");
+    assert_eq!(links[0]["url"], "https://docs.example.com");
+    assert_eq!(links[1]["type"], "issue");
+    assert_eq!(links[1]["url"], "https://issues.example.com/PAY-12");
+    assert_eq!(links[2]["type"], "tms");
+    assert_eq!(links[2]["url"], "TC-1");
+
+    assert_eq!(result["description"], "Refunds **part** of a payment");
+    assert_eq!(result["parameters"][0]["name"], "amount");
+    assert_eq!(result["parameters"][0]["value"], "42");
+}
+
+#[test]
+#[serial]
+fn test_parameters_change_history_id() {
+    let results = with_results_dir(|| {
+        for value in [1, 2] {
+            start_test_with_function("Param", None, Some("crate::module"), Some("param"));
+            parameter("value", value);
+            end_test("Param", Ok(()));
+        }
+    });
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["testCaseId"], results[1]["testCaseId"]);
+    assert_ne!(results[0]["historyId"], results[1]["historyId"]);
+}
+
+#[test]
+#[serial]
+fn test_attachments_and_parameters_go_to_the_innermost_step() {
+    let results = with_results_dir(|| {
+        start_test("Attachments");
+        add_attachment("before", "test level");
+        start_step("Open page");
+        add_attachment("page", "outer step");
+        start_step("Take screenshot");
+        add_attachment("screenshot", "inner step");
+        parameter("width", 1280);
+        end_step(&Ok(()));
+        parameter("url", "/login");
+        end_step(&Ok(()));
+        add_attachment("after", "test level");
+        parameter("browser", "firefox");
+        end_test("Attachments", Ok(()));
+    });
+
+    let result = &results[0];
+    let names = |value: &Value| -> Vec<String> {
+        value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(names(&result["attachments"]), ["before", "after"]);
+    assert_eq!(names(&result["parameters"]), ["browser"]);
+
+    let outer = &result["steps"][0];
+    assert_eq!(names(&outer["attachments"]), ["page"]);
+    assert_eq!(names(&outer["parameters"]), ["url"]);
+    let inner = &outer["steps"][0];
+    assert_eq!(names(&inner["attachments"]), ["screenshot"]);
+    assert_eq!(names(&inner["parameters"]), ["width"]);
+}
+
+fn step_names(steps: &Value) -> Vec<String> {
+    steps
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|step| step["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+#[serial]
+fn test_steps_keep_start_order_after_nested_failure() {
+    let results = with_results_dir(|| {
+        start_test("Nesting");
+        start_step("First");
+        end_step(&Ok(()));
+        start_step("Outer");
+        start_step("Failing inner");
+        let failure = panic::catch_unwind(|| panic!("lookup failed"));
+        end_step(&failure);
+        start_step("Second inner");
+        end_step(&Ok(()));
+        end_step(&Ok(()));
+        start_step("Last");
+        end_step(&Ok(()));
+        end_test("Nesting", Ok(()));
+    });
+
+    let steps = &results[0]["steps"];
+    assert_eq!(step_names(steps), ["First", "Outer", "Last"]);
+    assert_eq!(
+        step_names(&steps[1]["steps"]),
+        ["Failing inner", "Second inner"]
+    );
+    assert_eq!(steps[1]["steps"][0]["status"], "broken");
+    assert_eq!(steps[1]["steps"][1]["status"], "passed");
+}
+
+#[test]
+#[serial]
+fn test_unbalanced_end_step_is_recorded() {
+    let results = with_results_dir(|| {
+        start_test("Unbalanced");
+        end_step(&Ok(()));
+        start_step("Outer");
+        // Another flow may not finish a step it did not start
+        let test = current_test();
+        thread::spawn(move || test.run(|| end_step(&Ok(()))))
+            .join()
+            .unwrap();
+        start_step("Inner");
+        end_step(&Ok(()));
+        end_step(&Ok(()));
+        end_test("Unbalanced", Ok(()));
+    });
+
+    assert_eq!(results[0]["status"], "passed");
+    let steps = &results[0]["steps"];
+    assert_eq!(step_names(steps), ["Unbalanced end_step()", "Outer"]);
+    assert_eq!(steps[0]["status"], "broken");
+    assert!(
+        steps[0]["statusDetails"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("without a matching start_step()")
+    );
+    assert_eq!(steps[1]["status"], "passed");
+    assert_eq!(
+        step_names(&steps[1]["steps"]),
+        ["Unbalanced end_step()", "Inner"]
+    );
+}
+
+#[test]
+#[serial]
+fn test_unfinished_steps_are_closed_as_broken() {
+    let results = with_results_dir(|| {
+        start_test("Unfinished");
+        start_step("Outer");
+        start_step("Inner");
+        end_test("Unfinished", Ok(()));
+    });
+
+    let outer = &results[0]["steps"][0];
+    for step in [outer, &outer["steps"][0]] {
+        assert_eq!(step["stage"], "finished");
+        assert_eq!(step["status"], "broken");
+        assert_eq!(
+            step["statusDetails"]["message"],
+            "Step did not finish before the test ended"
+        );
+    }
+}
+
+fn run_failing(name: &'static str, body: impl FnOnce() + panic::UnwindSafe) -> Value {
+    let results = with_results_dir(|| {
+        start_test(name);
+        let result = panic::catch_unwind(body);
+        end_test(name, &result);
+    });
+    results.into_iter().next().unwrap()
+}
+
+#[test]
+#[serial]
+fn test_assertion_failures_are_failed() {
+    let result = run_failing("assert_eq", || assert_eq!(1 + 1, 3));
+    assert_eq!(result["status"], "failed");
+
+    let result = run_failing("assert", || assert!(1 > 2));
+    assert_eq!(result["status"], "failed");
+
+    let result = run_failing("assert with message", || {
+        let value = 1;
+        assert!(value > 2, "value {} is too small", value);
+    });
+    assert_eq!(result["status"], "failed");
+    assert_eq!(result["statusDetails"]["message"], "value 1 is too small");
+    assert!(
+        result["statusDetails"]["trace"]
+            .as_str()
+            .unwrap()
+            .contains("runtime_tests.rs")
+    );
+}
+
+#[test]
+#[serial]
+fn test_other_panics_are_broken() {
+    let result = run_failing("unwrap", || {
+        let mut values: Vec<i32> = Vec::new();
+        values.pop().unwrap();
+    });
+    assert_eq!(result["status"], "broken");
+
+    let result = run_failing("index", || {
+        let values: Vec<i32> = Vec::new();
+        let index = values.len() + 1;
+        let _ = values[index];
+    });
+    assert_eq!(result["status"], "broken");
+
+    let result = run_failing("panic", || panic!("setup failed"));
+    assert_eq!(result["status"], "broken");
+    assert_eq!(result["statusDetails"]["message"], "setup failed");
+
+    // Only the assertion itself is a failure, not what its operands run into
+    let result = run_failing("panic in assertion", || {
+        let mut values: Vec<i32> = Vec::new();
+        assert!(values.pop().unwrap() > 0, "no values");
+    });
+    assert_eq!(result["status"], "broken");
+}
+
+#[test]
+#[serial]
+fn test_trace_contains_test_frames_only() {
+    fn load_fixture() -> i32 {
+        let fixtures: Vec<i32> = Vec::new();
+        fixtures.first().copied().unwrap()
+    }
+
+    let result = run_failing("trace", || {
+        load_fixture();
+    });
+
+    let trace = result["statusDetails"]["trace"].as_str().unwrap();
+    assert!(trace.starts_with("panicked at "));
+    assert!(trace.contains("load_fixture"));
+    assert!(!trace.contains(": std::"));
+    assert!(!trace.contains(": core::"));
+    assert!(!trace.contains(": allure_rust_core::"));
+    assert!(!trace.contains("__rust_begin_short_backtrace"));
+}
+
+#[test]
+#[serial]
+fn test_should_panic_status() {
+    let results = with_results_dir(|| {
+        start_test("expected panic");
+        let result = panic::catch_unwind(|| panic!("refund rejected: amount too large"));
+        end_test_expecting_panic("expected panic", &result, Some("amount too large"));
+    });
+    assert_eq!(results[0]["status"], "passed");
+
+    let results = with_results_dir(|| {
+        start_test("unexpected message");
+        let result = panic::catch_unwind(|| panic!("connection refused"));
+        end_test_expecting_panic("unexpected message", &result, Some("amount too large"));
+    });
+    assert_eq!(results[0]["status"], "failed");
+    assert!(
+        results[0]["statusDetails"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("panic did not contain expected string")
+    );
+
+    let results = with_results_dir(|| {
+        start_test("no panic");
+        end_test_expecting_panic("no panic", &Ok(()), None);
+    });
+    assert_eq!(results[0]["status"], "failed");
+    assert_eq!(
+        results[0]["statusDetails"]["message"],
+        "test did not panic as expected"
+    );
+}
@@ -0,0 +1,24 @@
+mod common;
+
+use allure_rust_core::{end_test, start_test_with_function};
+use common::{has_label, with_results_dir};
+
+// The mapping is read once per process, so it gets a test binary of its own
+#[test]
+fn test_suite_mapping_changes_the_levels() {
+    std::env::set_var("ALLURE_SUITE_MAPPING", "-,suite,subSuite");
+    let results = with_results_dir(|| {
+        start_test_with_function(
+            "Suites",
+            None,
+            Some("shop::orders::refunds::tests"),
+            Some("suites"),
+        );
+        end_test("Suites", Ok(()));
+    });
+
+    let labels = results[0]["labels"].as_array().unwrap();
+    assert!(!labels.iter().any(|label| label["name"] == "parentSuite"));
+    assert!(has_label(&results[0], "suite", "orders"));
+    assert!(has_label(&results[0], "subSuite", "refunds.tests"));
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use allure_rust::{add_attachment, allure_step, allure_test, current_test, parameter, step};
+use common::with_results_dir;
+use serial_test::serial;
+
+#[step("Validate chunk")]
+fn validate_chunk(index: usize) {
+    assert!(index < 10);
+}
+
+#[test]
+#[serial]
+fn test_spawned_threads_record_into_the_test() {
+    #[allure_test]
+    fn with_spawned_thread() {
+        allure_step!("Fan out", {
+            allure_rust::thread::spawn(|| {
+                validate_chunk(0);
+                add_attachment("chunk", "0");
+                parameter("workers", 1);
+            })
+            .join()
+            .unwrap();
+        });
+    }
+
+    let results = with_results_dir(with_spawned_thread);
+
+    assert_eq!(results.len(), 1);
+    let result = &results[0];
+    let fan_out = &result["steps"][0];
+    assert_eq!(fan_out["name"], "Fan out");
+    assert_eq!(fan_out["steps"][0]["name"], "Validate chunk");
+    assert_eq!(fan_out["attachments"][0]["name"], "chunk");
+    assert_eq!(fan_out["parameters"][0]["name"], "workers");
+}
+
+#[test]
+#[serial]
+fn test_scoped_threads_record_into_the_test() {
+    #[allure_test]
+    fn with_scoped_threads() {
+        std::thread::scope(|scope| {
+            for index in 0..3 {
+                allure_rust::thread::spawn_scoped(scope, move || validate_chunk(index));
+            }
+        });
+    }
+
+    let results = with_results_dir(with_scoped_threads);
+
+    let steps = results[0]["steps"].as_array().unwrap();
+    assert_eq!(steps.len(), 3);
+    assert!(steps.iter().all(|step| step["status"] == "passed"));
+}
+
+#[test]
+#[serial]
+fn test_handle_runs_code_in_the_test() {
+    #[allure_test]
+    fn with_handle() {
+        let test = current_test();
+        std::thread::spawn(move || {
+            test.run(|| allure_step!("From a handle", {}));
+            // Outside of `run` the thread is not part of the test
+            allure_step!("Lost", {});
+        })
+        .join()
+        .unwrap();
+    }
+
+    let results = with_results_dir(with_handle);
+
+    let steps = results[0]["steps"].as_array().unwrap();
+    assert_eq!(steps.len(), 1);
+    assert_eq!(steps[0]["name"], "From a handle");
+}
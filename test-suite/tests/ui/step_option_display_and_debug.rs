@@ -0,0 +1,8 @@
+use allure_rust::step;
+
+#[step("Log in", display(user), debug(user))]
+fn login(user: &str) {
+    let _ = user;
+}
+
+fn main() {}
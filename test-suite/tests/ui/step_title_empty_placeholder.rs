@@ -0,0 +1,8 @@
+use allure_rust::step;
+
+#[step("Transfer {}")]
+fn transfer(amount: u32) {
+    let _ = amount;
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use allure_rust::step;
+
+#[step("Open {page}")]
+fn open() {}
+
+fn main() {}
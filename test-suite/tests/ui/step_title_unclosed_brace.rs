@@ -0,0 +1,8 @@
+use allure_rust::step;
+
+#[step("Transfer {amount")]
+fn transfer(amount: u32) {
+    let _ = amount;
+}
+
+fn main() {}
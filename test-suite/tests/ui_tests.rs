@@ -0,0 +1,12 @@
+// Compile errors of the macros, checked against the expected output in tests/ui
+#[test]
+fn test_step_title_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/step_title_*.rs");
+}
+
+#[test]
+fn test_step_option_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/step_option_*.rs");
+}
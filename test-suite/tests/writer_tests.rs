@@ -16,9 +16,12 @@ fn test_write_test_result() {
 
     let test_result = TestResult {
         uuid: Uuid::new_v4(),
-        history_id: Uuid::new_v4(),
+        history_id: Uuid::new_v4().to_string(),
+        test_case_id: Uuid::new_v4().to_string(),
         name: "Test write result".to_string(),
+        full_name: "tests::example".to_string(),
         description: None,
+        description_html: None,
         status: Status::Passed,
         status_details: None,
         stage: "finished".to_string(),
@@ -113,9 +116,12 @@ fn test_results_directory_creation() {
 
     let test_result = TestResult {
         uuid: Uuid::new_v4(),
-        history_id: Uuid::new_v4(),
+        history_id: Uuid::new_v4().to_string(),
+        test_case_id: Uuid::new_v4().to_string(),
         name: "Test directory creation".to_string(),
+        full_name: "tests::example".to_string(),
         description: None,
+        description_html: None,
         status: Status::Passed,
         status_details: None,
         stage: "finished".to_string(),
@@ -145,9 +151,12 @@ fn test_multiple_test_results() {
     for i in 0..5 {
         let test_result = TestResult {
             uuid: Uuid::new_v4(),
-            history_id: Uuid::new_v4(),
+            history_id: Uuid::new_v4().to_string(),
+            test_case_id: Uuid::new_v4().to_string(),
             name: format!("Test {}", i),
+            full_name: "tests::example".to_string(),
             description: None,
+            description_html: None,
             status: Status::Passed,
             status_details: None,
             stage: "finished".to_string(),